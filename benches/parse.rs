@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use json_reader::{parse_json_string_by_tokens, parse_json_string_with, ParserOptions};
+
+// An array of `records` small objects, like a typical API listing
+fn large_document(records: usize) -> String {
+    let items: Vec<String> = (0..records)
+        .map(|i| {
+            format!(
+                r#"{{"id": {}, "name": "item \"{}\"", "price": {}.5, "tags": ["a", "b"], "active": true, "parent": null}}"#,
+                i, i, i
+            )
+        })
+        .collect();
+
+    format!("[{}]", items.join(", "))
+}
+
+// Alternating arrays and objects nested `depth` levels deep
+fn deeply_nested_document(depth: usize) -> String {
+    let mut json_string = String::from("1");
+
+    for i in 0..depth {
+        json_string = if i % 2 == 0 {
+            format!("[{}]", json_string)
+        } else {
+            format!(r#"{{"key": {}}}"#, json_string)
+        };
+    }
+
+    json_string
+}
+
+fn bench_inputs(c: &mut Criterion, group_name: &str, inputs: Vec<(usize, String)>) {
+    let mut group = c.benchmark_group(group_name);
+    // The token folding parser has no depth limit, lift it to compare the two on deep input
+    let options = ParserOptions {
+        max_depth: usize::MAX,
+        ..ParserOptions::default()
+    };
+
+    for (size, json_string) in &inputs {
+        group.bench_with_input(
+            BenchmarkId::new("recursive_descent", size),
+            json_string,
+            |b, json_string| {
+                b.iter(|| parse_json_string_with(black_box(json_string), &options).unwrap())
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("token_folding", size),
+            json_string,
+            |b, json_string| {
+                b.iter(|| parse_json_string_by_tokens(black_box(json_string)).unwrap())
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn large(c: &mut Criterion) {
+    let inputs = [100, 1_000]
+        .into_iter()
+        .map(|records| (records, large_document(records)))
+        .collect();
+
+    bench_inputs(c, "large", inputs);
+}
+
+fn deeply_nested(c: &mut Criterion) {
+    let inputs = [100, 1_000]
+        .into_iter()
+        .map(|depth| (depth, deeply_nested_document(depth)))
+        .collect();
+
+    bench_inputs(c, "deeply_nested", inputs);
+}
+
+criterion_group!(benches, large, deeply_nested);
+criterion_main!(benches);
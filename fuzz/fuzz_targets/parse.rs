@@ -0,0 +1,62 @@
+#![no_main]
+
+use json_reader::*;
+use libfuzzer_sys::fuzz_target;
+
+fn push_in_halves(data: &[u8]) -> Result<JsonValue, JsonError> {
+    let mut parser = JsonPushParser::new();
+    let (first, second) = data.split_at(data.len() / 2);
+
+    for half in [first, second] {
+        parser.feed(half);
+        if let Progress::Ready(value) = parser.next_value()? {
+            return Ok(value);
+        }
+    }
+    parser.finish();
+
+    match parser.next_value()? {
+        Progress::Ready(value) => Ok(value),
+        _ => unreachable!("the parser ends with a value or an error"),
+    }
+}
+
+// Every public way of reading text has to return instead of panicking, and whatever parses has
+// to serialize to text that parses back to an equal value. Bytes are read the same from a slice,
+// a reader and pushed pieces
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(parse_json_slice(data), parse_json_reader(data));
+    assert_eq!(parse_json_slice(data), push_in_halves(data));
+
+    let Ok(json_string) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = tokenize_json_string(json_string);
+    let _ = from_json_string(json_string);
+    let _ = parse_json_string_by_tokens(json_string);
+    let _ = parse_json_prefix(json_string);
+
+    for duplicate_keys in [
+        DuplicateKeys::Error,
+        DuplicateKeys::FirstWins,
+        DuplicateKeys::CollectAll,
+    ] {
+        let options = ParserOptions {
+            duplicate_keys,
+            max_depth: 16,
+            max_string_bytes: Some(16),
+            max_members: Some(16),
+            max_input_bytes: Some(1024),
+        };
+        let _ = parse_json_string_with(json_string, &options);
+    }
+
+    if let Ok(value) = parse_json_string(json_string) {
+        assert_eq!(Ok(&value), parse_json_string(&value.to_string()).as_ref());
+        assert_eq!(
+            Ok(&value),
+            parse_json_string(&value.to_pretty_string(&PrettyConfig::default())).as_ref()
+        );
+    }
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+use json_reader::*;
+use libfuzzer_sys::fuzz_target;
+
+// Token lists don't have to come from the tokenizer, any sequence has to give a value or an
+// error
+fuzz_target!(|data: &[u8]| {
+    let json_token_vec: Vec<Token> = data
+        .iter()
+        .map(|byte| match byte % 12 {
+            0 => Token::JsonKey(String::from("k")),
+            1 => Token::JsonString(String::from("s")),
+            2 => Token::JsonNum(f64::from(*byte)),
+            3 => Token::JsonBool(byte % 2 == 0),
+            4 => Token::JsonNull,
+            5 => Token::JsonArrBeg,
+            6 => Token::JsonArrEnd,
+            7 => Token::JsonObjBeg,
+            8 => Token::JsonObjEnd,
+            9 => Token::JsonComma,
+            10 => Token::JsonColon,
+            _ => Token::JsonKey(String::from("k2")),
+        })
+        .collect();
+
+    let _ = json_tokens_to_json_object(&json_token_vec);
+});
@@ -0,0 +1,642 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+#[cfg(feature = "jsonpath")]
+use crate::JsonPath;
+use crate::{pointer::path_pointer, JsonMap, JsonValue, PathElement, PrettyConfig};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// How `JsonDiff` pairs up the elements of two arrays.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum ArrayMatching {
+    /// The elements at the same index are compared, extra ones are added or removed.
+    #[default]
+    ByIndex,
+    /// Elements are objects paired by the value of this member, like `"id"`, so reordering
+    /// them isn't a change. Arrays with an element that isn't an object with the member are
+    /// compared by index.
+    ByKey(String),
+}
+
+/// Settings for `JsonDiff::with_options`. The default compares arrays by index and numbers
+/// exactly, and ignores nothing.
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub arrays: ArrayMatching,
+    /// Numbers that differ by at most this much count as equal.
+    pub numeric_tolerance: f64,
+    /// Values selected by any of these queries in either document are left out, along with
+    /// everything inside them. `$..updated_at` skips every member of that name. Needs the
+    /// `jsonpath` feature.
+    #[cfg(feature = "jsonpath")]
+    pub ignored_paths: Vec<JsonPath>,
+}
+
+/// One difference between two documents, with the JSON Pointer of the value in the document
+/// it is in: `from` in the first one, `to` in the second. A changed value has both, which
+/// differ when it is in an array element matched by key that moved.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonChange {
+    Added {
+        to: String,
+        value: JsonValue,
+    },
+    Removed {
+        from: String,
+        value: JsonValue,
+    },
+    Changed {
+        from: String,
+        to: String,
+        old: JsonValue,
+        new: JsonValue,
+    },
+}
+
+impl JsonChange {
+    /// The path in the second document, or in the first one for a removed value.
+    pub fn path(&self) -> &str {
+        match self {
+            JsonChange::Added { to, .. } | JsonChange::Changed { to, .. } => to,
+            JsonChange::Removed { from, .. } => from,
+        }
+    }
+
+    fn to_value(&self) -> JsonValue {
+        let (change, from, to, old, new) = match self {
+            JsonChange::Added { to, value } => ("added", None, Some(to), None, Some(value)),
+            JsonChange::Removed { from, value } => ("removed", Some(from), None, Some(value), None),
+            JsonChange::Changed { from, to, old, new } => {
+                ("changed", Some(from), Some(to), Some(old), Some(new))
+            }
+        };
+
+        let mut map = JsonMap::new();
+        map.insert(String::from("change"), JsonValue::from(change));
+        let members = [
+            ("from", from.map(|from| JsonValue::from(from.as_str()))),
+            ("to", to.map(|to| JsonValue::from(to.as_str()))),
+            ("old", old.cloned()),
+            ("new", new.cloned()),
+        ];
+        for (name, value) in members {
+            if let Some(value) = value {
+                map.insert(String::from(name), value);
+            }
+        }
+        JsonValue::JsonObj(map)
+    }
+
+    // The header of the change in the report, with both paths when they differ
+    fn header(&self) -> String {
+        match self {
+            JsonChange::Changed { from, to, .. } if from != to => {
+                format!("@@ -{} +{} @@", from, to)
+            }
+            _ => format!("@@ {} @@", self.path()),
+        }
+    }
+}
+
+/// The structural differences between two documents: the paths added, removed and changed.
+///
+/// A value that changed type, or a scalar that changed, is one `Changed` entry. Objects and
+/// arrays on both sides are compared member by member instead. The changes come in document
+/// order, those of an object's existing members before the members added to it.
+///
+/// `Display` writes a unified report, one `@@ path @@` header per change followed by the old
+/// value on `-` lines and the new one on `+` lines, and `to_value` gives the same as JSON.
+///
+/// The comparison takes no recursion, so deep documents don't overflow the stack. A
+/// `JsonMap` from `from_json_string` is compared as `JsonValue::from(map)`.
+///
+/// ```
+/// use json_reader::{parse_json_string, JsonDiff};
+///
+/// let expected = parse_json_string(r#"{"name": "a", "size": 1, "tags": ["x"]}"#)?;
+/// let actual = parse_json_string(r#"{"name": "a", "size": 2, "tags": ["x", "y"]}"#)?;
+/// let diff = JsonDiff::new(&expected, &actual);
+///
+/// assert_eq!("@@ /size @@\n- 1\n+ 2\n@@ /tags/1 @@\n+ \"y\"\n", diff.to_string());
+/// # Ok::<(), json_reader::JsonError>(())
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct JsonDiff {
+    changes: Vec<JsonChange>,
+}
+
+impl JsonDiff {
+    pub fn new(from: &JsonValue, to: &JsonValue) -> JsonDiff {
+        JsonDiff::with_options(from, to, &DiffOptions::default())
+    }
+
+    pub fn with_options(from: &JsonValue, to: &JsonValue, options: &DiffOptions) -> JsonDiff {
+        #[cfg(feature = "jsonpath")]
+        let ignored = |value: &JsonValue| -> HashSet<Vec<PathElement>> {
+            options
+                .ignored_paths
+                .iter()
+                .flat_map(|path| path.query(value))
+                .map(|node| node.path().to_vec())
+                .collect()
+        };
+        #[cfg(not(feature = "jsonpath"))]
+        let ignored = |_: &JsonValue| HashSet::new();
+
+        let mut differ = Differ {
+            options,
+            ignored_from: ignored(from),
+            ignored_to: ignored(to),
+            changes: Vec::new(),
+        };
+        differ.diff(from, to);
+
+        JsonDiff {
+            changes: differ.changes,
+        }
+    }
+
+    pub fn changes(&self) -> &[JsonChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The changes as a JSON array, with objects like
+    /// `{"change": "changed", "from": "/size", "to": "/size", "old": 1, "new": 2}`. `from` is
+    /// the path in the first document and `to` the one in the second, so added values only have
+    /// `to` and `new`, and removed ones only `from` and `old`.
+    pub fn to_value(&self) -> JsonValue {
+        JsonValue::JsonArr(self.changes.iter().map(JsonChange::to_value).collect())
+    }
+
+    /// The unified report, with ANSI colors for a terminal when `color` is set: removed lines
+    /// in red, added ones in green and the headers in cyan.
+    pub fn report(&self, color: bool) -> String {
+        let config = PrettyConfig {
+            short_array_width: Some(60),
+            ..PrettyConfig::default()
+        };
+        let paint = |out: &mut String, code: &str, line: &str| {
+            if color {
+                out.push_str(code);
+                out.push_str(line);
+                out.push_str(RESET);
+            } else {
+                out.push_str(line);
+            }
+            out.push('\n');
+        };
+        let value_lines = |out: &mut String, sign: char, value: &JsonValue| {
+            let code = if sign == '-' { RED } else { GREEN };
+            for line in value.to_pretty_string(&config).lines() {
+                paint(out, code, &format!("{} {}", sign, line));
+            }
+        };
+
+        let mut out = String::new();
+        for change in &self.changes {
+            paint(&mut out, CYAN, &change.header());
+            match change {
+                JsonChange::Added { value, .. } => value_lines(&mut out, '+', value),
+                JsonChange::Removed { value, .. } => value_lines(&mut out, '-', value),
+                JsonChange::Changed { old, new, .. } => {
+                    value_lines(&mut out, '-', old);
+                    value_lines(&mut out, '+', new);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Writes the report without colors.
+impl fmt::Display for JsonDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.report(false))
+    }
+}
+
+struct Differ<'o> {
+    options: &'o DiffOptions,
+    ignored_from: HashSet<Vec<PathElement>>,
+    ignored_to: HashSet<Vec<PathElement>>,
+    changes: Vec<JsonChange>,
+}
+
+// A step of the comparison. Steps go on a stack, so that deep nesting costs no recursion
+enum Step<'v> {
+    // Compares two values at the current paths
+    Compare(&'v JsonValue, &'v JsonValue),
+    // Steps into a member or element on both sides, and back out
+    Enter(PathElement, PathElement),
+    Leave,
+    Removed(PathElement, &'v JsonValue),
+    Added(PathElement, &'v JsonValue),
+}
+
+impl<'v> Step<'v> {
+    // The steps comparing two members or elements
+    fn nested(
+        from_element: PathElement,
+        to_element: PathElement,
+        from: &'v JsonValue,
+        to: &'v JsonValue,
+    ) -> [Step<'v>; 3] {
+        [
+            Step::Enter(from_element, to_element),
+            Step::Compare(from, to),
+            Step::Leave,
+        ]
+    }
+}
+
+impl Differ<'_> {
+    fn diff(&mut self, from: &JsonValue, to: &JsonValue) {
+        let mut from_path = Vec::new();
+        let mut to_path = Vec::new();
+        let mut stack = vec![Step::Compare(from, to)];
+
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Compare(from, to) => {
+                    if self.ignored_from.contains(&from_path) || self.ignored_to.contains(&to_path)
+                    {
+                        continue;
+                    }
+                    // The steps are pushed in reverse, so that they are taken in order
+                    let steps = self.compare(from, to, &from_path, &to_path);
+                    stack.extend(steps.into_iter().rev());
+                }
+                Step::Enter(from_element, to_element) => {
+                    from_path.push(from_element);
+                    to_path.push(to_element);
+                }
+                Step::Leave => {
+                    from_path.pop();
+                    to_path.pop();
+                }
+                Step::Removed(element, value) => {
+                    from_path.push(element);
+                    if !self.ignored_from.contains(&from_path) {
+                        self.changes.push(JsonChange::Removed {
+                            from: path_pointer(&from_path),
+                            value: value.clone(),
+                        });
+                    }
+                    from_path.pop();
+                }
+                Step::Added(element, value) => {
+                    to_path.push(element);
+                    if !self.ignored_to.contains(&to_path) {
+                        self.changes.push(JsonChange::Added {
+                            to: path_pointer(&to_path),
+                            value: value.clone(),
+                        });
+                    }
+                    to_path.pop();
+                }
+            }
+        }
+    }
+
+    // Compares two values, giving the steps that compare what they hold
+    fn compare<'v>(
+        &mut self,
+        from: &'v JsonValue,
+        to: &'v JsonValue,
+        from_path: &[PathElement],
+        to_path: &[PathElement],
+    ) -> Vec<Step<'v>> {
+        match (from, to) {
+            (JsonValue::JsonNum(a), JsonValue::JsonNum(b))
+                if (a - b).abs() <= self.options.numeric_tolerance =>
+            {
+                Vec::new()
+            }
+            (JsonValue::JsonObj(from), JsonValue::JsonObj(to)) => compare_objects(from, to),
+            (JsonValue::JsonArr(from), JsonValue::JsonArr(to)) => match &self.options.arrays {
+                ArrayMatching::ByKey(key) if keyed(from, key) && keyed(to, key) => {
+                    compare_by_key(from, to, key)
+                }
+                _ => compare_by_index(from, to),
+            },
+            _ if from == to => Vec::new(),
+            _ => {
+                self.changes.push(JsonChange::Changed {
+                    from: path_pointer(from_path),
+                    to: path_pointer(to_path),
+                    old: from.clone(),
+                    new: to.clone(),
+                });
+                Vec::new()
+            }
+        }
+    }
+}
+
+fn compare_objects<'v>(from: &'v JsonMap, to: &'v JsonMap) -> Vec<Step<'v>> {
+    let mut steps = Vec::new();
+    for (key, old) in from.iter() {
+        match to.get(key) {
+            Some(new) => steps.extend(Step::nested(
+                PathElement::Name(key.clone()),
+                PathElement::Name(key.clone()),
+                old,
+                new,
+            )),
+            None => steps.push(Step::Removed(PathElement::Name(key.clone()), old)),
+        }
+    }
+
+    for (key, new) in to.iter() {
+        if !from.contains_key(key) {
+            steps.push(Step::Added(PathElement::Name(key.clone()), new));
+        }
+    }
+    steps
+}
+
+fn compare_by_index<'v>(from: &'v [JsonValue], to: &'v [JsonValue]) -> Vec<Step<'v>> {
+    let mut steps = Vec::new();
+    for (i, (old, new)) in from.iter().zip(to).enumerate() {
+        steps.extend(Step::nested(
+            PathElement::Index(i),
+            PathElement::Index(i),
+            old,
+            new,
+        ));
+    }
+
+    for (i, old) in from.iter().enumerate().skip(to.len()) {
+        steps.push(Step::Removed(PathElement::Index(i), old));
+    }
+    for (i, new) in to.iter().enumerate().skip(from.len()) {
+        steps.push(Step::Added(PathElement::Index(i), new));
+    }
+    steps
+}
+
+fn compare_by_key<'v>(from: &'v [JsonValue], to: &'v [JsonValue], key: &str) -> Vec<Step<'v>> {
+    // The indexes of the new elements by their key, in reverse so that elements with the same
+    // key are paired in order
+    let mut unmatched: HashMap<String, Vec<usize>> = HashMap::new();
+    for (j, new) in to.iter().enumerate().rev() {
+        unmatched.entry(key_of(new, key)).or_default().push(j);
+    }
+
+    let mut steps = Vec::new();
+    let mut matched = vec![false; to.len()];
+    for (i, old) in from.iter().enumerate() {
+        match unmatched.get_mut(&key_of(old, key)).and_then(Vec::pop) {
+            Some(j) => {
+                matched[j] = true;
+                steps.extend(Step::nested(
+                    PathElement::Index(i),
+                    PathElement::Index(j),
+                    old,
+                    &to[j],
+                ));
+            }
+            None => steps.push(Step::Removed(PathElement::Index(i), old)),
+        }
+    }
+
+    for (j, new) in to.iter().enumerate() {
+        if !matched[j] {
+            steps.push(Step::Added(PathElement::Index(j), new));
+        }
+    }
+    steps
+}
+
+// Whether every element is an object with the key member
+fn keyed(elements: &[JsonValue], key: &str) -> bool {
+    elements
+        .iter()
+        .all(|element| matches!(element, JsonValue::JsonObj(map) if map.contains_key(key)))
+}
+
+// The key member of an element, as JSON text so that it can be hashed
+fn key_of(element: &JsonValue, key: &str) -> String {
+    match element {
+        JsonValue::JsonObj(map) => map.get(key).map(JsonValue::to_string).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json_string;
+
+    fn json(json_string: &str) -> JsonValue {
+        parse_json_string(json_string).unwrap()
+    }
+
+    fn paths(diff: &JsonDiff) -> Vec<&str> {
+        diff.changes().iter().map(JsonChange::path).collect()
+    }
+
+    #[test]
+    fn lists_added_removed_and_changed_paths() {
+        let diff = JsonDiff::new(
+            &json(r#"{"a": 1, "b": {"c": [1, 2, 3], "d": "x"}, "e": null, "f": [true]}"#),
+            &json(r#"{"a": 1, "b": {"c": [1, 5], "d": 1}, "f": [true, {"g": 0}], "h": []}"#),
+        );
+
+        assert_eq!(
+            &[
+                JsonChange::Changed {
+                    from: String::from("/b/c/1"),
+                    to: String::from("/b/c/1"),
+                    old: json("2"),
+                    new: json("5"),
+                },
+                JsonChange::Removed {
+                    from: String::from("/b/c/2"),
+                    value: json("3"),
+                },
+                JsonChange::Changed {
+                    from: String::from("/b/d"),
+                    to: String::from("/b/d"),
+                    old: json(r#""x""#),
+                    new: json("1"),
+                },
+                JsonChange::Removed {
+                    from: String::from("/e"),
+                    value: json("null"),
+                },
+                JsonChange::Added {
+                    to: String::from("/f/1"),
+                    value: json(r#"{"g": 0}"#),
+                },
+                JsonChange::Added {
+                    to: String::from("/h"),
+                    value: json("[]"),
+                },
+            ][..],
+            diff.changes()
+        );
+        assert!(JsonDiff::new(&json(r#"{"a": [1, {}]}"#), &json(r#"{"a": [1, {}]}"#)).is_empty());
+        assert_eq!(vec![""], paths(&JsonDiff::new(&json("[1]"), &json("{}"))));
+    }
+
+    #[test]
+    fn matches_array_elements_by_key() {
+        let from = json(
+            r#"{"users": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}, {"id": 3, "name": "c"}]}"#,
+        );
+        let to = json(
+            r#"{"users": [{"id": 3, "name": "c"}, {"id": 4, "name": "d"}, {"id": 1, "name": "A"}]}"#,
+        );
+        let options = DiffOptions {
+            arrays: ArrayMatching::ByKey(String::from("id")),
+            ..DiffOptions::default()
+        };
+
+        // The paths of each change say which document they are in, as elements moved
+        let diff = JsonDiff::with_options(&from, &to, &options);
+        assert_eq!(
+            json(
+                r#"[
+                    {"change": "changed", "from": "/users/0/name", "to": "/users/2/name", "old": "a", "new": "A"},
+                    {"change": "removed", "from": "/users/1", "old": {"id": 2, "name": "b"}},
+                    {"change": "added", "to": "/users/1", "new": {"id": 4, "name": "d"}}
+                ]"#
+            ),
+            diff.to_value()
+        );
+        assert!(diff
+            .to_string()
+            .starts_with("@@ -/users/0/name +/users/2/name @@\n- \"a\"\n+ \"A\"\n"));
+        assert_eq!(
+            vec![
+                "/users/0/id",
+                "/users/0/name",
+                "/users/1/id",
+                "/users/1/name",
+                "/users/2/id",
+                "/users/2/name"
+            ],
+            paths(&JsonDiff::new(&from, &to))
+        );
+
+        // Without the key on every element, arrays fall back to comparing by index
+        let diff = JsonDiff::with_options(
+            &json(r#"[{"id": 1}, 2]"#),
+            &json(r#"[2, {"id": 1}]"#),
+            &options,
+        );
+        assert_eq!(vec!["/0", "/1"], paths(&diff));
+    }
+
+    #[test]
+    fn allows_for_numeric_tolerance() {
+        let from = json(r#"{"x": 0.1, "y": [1.0, 2.0]}"#);
+        let to = json(r#"{"x": 0.1000001, "y": [1.01, 2.0]}"#);
+        let options = DiffOptions {
+            numeric_tolerance: 1e-3,
+            ..DiffOptions::default()
+        };
+
+        assert_eq!(vec!["/x", "/y/0"], paths(&JsonDiff::new(&from, &to)));
+        assert_eq!(
+            vec!["/y/0"],
+            paths(&JsonDiff::with_options(&from, &to, &options))
+        );
+    }
+
+    #[cfg(feature = "jsonpath")]
+    #[test]
+    fn leaves_out_ignored_paths() {
+        let from = json(
+            r#"{"updated_at": 1, "items": [{"id": 1, "meta": {"etag": "a"}}], "debug": true}"#,
+        );
+        let to = json(
+            r#"{"updated_at": 2, "items": [{"id": 2, "meta": {"etag": "b"}}, {"id": 3, "meta": 0}]}"#,
+        );
+        let options = DiffOptions {
+            ignored_paths: vec![
+                JsonPath::parse("$..updated_at").unwrap(),
+                JsonPath::parse("$.items[*].meta").unwrap(),
+                JsonPath::parse("$.debug").unwrap(),
+            ],
+            ..DiffOptions::default()
+        };
+
+        assert_eq!(
+            vec!["/items/0/id", "/items/1"],
+            paths(&JsonDiff::with_options(&from, &to, &options))
+        );
+    }
+
+    #[test]
+    fn renders_a_unified_report_and_a_list() {
+        let diff = JsonDiff::new(
+            &json(r#"{"port": 80, "tls": {"on": false}, "hosts": ["a"]}"#),
+            &json(r#"{"port": 8080, "hosts": ["a", "b"], "tls2": {"on": true}}"#),
+        );
+
+        assert_eq!(
+            "@@ /port @@\n\
+             - 80\n\
+             + 8080\n\
+             @@ /tls @@\n\
+             - {\n\
+             -   \"on\": false\n\
+             - }\n\
+             @@ /hosts/1 @@\n\
+             + \"b\"\n\
+             @@ /tls2 @@\n\
+             + {\n\
+             +   \"on\": true\n\
+             + }\n",
+            diff.to_string()
+        );
+        assert_eq!(
+            "\x1b[36m@@ /port @@\x1b[0m\n\x1b[31m- 80\x1b[0m\n\x1b[32m+ 8080\x1b[0m\n",
+            JsonDiff::new(&json(r#"{"port": 80}"#), &json(r#"{"port": 8080}"#)).report(true)
+        );
+        assert_eq!(
+            json(
+                r#"[
+                    {"change": "changed", "from": "/port", "to": "/port", "old": 80, "new": 8080},
+                    {"change": "removed", "from": "/tls", "old": {"on": false}},
+                    {"change": "added", "to": "/hosts/1", "new": "b"},
+                    {"change": "added", "to": "/tls2", "new": {"on": true}}
+                ]"#
+            ),
+            diff.to_value()
+        );
+    }
+
+    #[test]
+    fn compares_deep_documents_without_recursion() {
+        let nest = |leaf: f64| {
+            (0..100_000).fold(JsonValue::JsonNum(leaf), |value, _| {
+                JsonValue::JsonArr(vec![value])
+            })
+        };
+        let (from, to) = (nest(1.0), nest(2.0));
+
+        let diff = JsonDiff::new(&from, &to);
+        assert_eq!(1, diff.changes().len());
+        assert_eq!(100_000 * 2, diff.changes()[0].path().len());
+
+        // Dropping the documents would recurse as deep
+        for mut value in [from, to] {
+            while let JsonValue::JsonArr(elements) = &mut value {
+                value = elements.pop().unwrap();
+            }
+        }
+    }
+}
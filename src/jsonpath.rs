@@ -0,0 +1,1505 @@
+use std::{borrow::Cow, fmt, rc::Rc, str::FromStr};
+
+use regex::Regex;
+
+use crate::{pointer::path_pointer, JsonValue, PathElement};
+
+// Indexes and slice bounds have to be integers I-JSON can hold exactly
+const MAX_INTEGER: i64 = (1 << 53) - 1;
+// How deeply filters, parentheses and function calls can nest, as for documents by default
+const MAX_DEPTH: usize = 128;
+
+/// Why a JSONPath query was rejected.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum JsonPathErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    InvalidEscape,
+    /// A number that isn't valid here, like `01`, `-0` as an index, or an index beyond what
+    /// I-JSON can hold.
+    InvalidNumber,
+    UnknownFunction(String),
+    /// A function called with the wrong number of arguments.
+    ArgumentCount(String),
+    /// A function argument of the wrong type, like a query that can select more than one node
+    /// where a single value is expected.
+    ArgumentType(String),
+    /// A query compared with something, which can select more than one node.
+    NotSingular,
+    /// A function compared with something, which doesn't give a value.
+    NotComparable,
+    /// A filter that is a literal, or a function that doesn't give true or false.
+    NotATest,
+    /// Filters, parentheses and function calls nested more than 128 deep, at the first one
+    /// too deep.
+    TooDeep,
+}
+
+impl fmt::Display for JsonPathErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonPathErrorKind::UnexpectedCharacter(ch) => {
+                write!(f, "unexpected character {:?}", ch)
+            }
+            JsonPathErrorKind::UnexpectedEnd => write!(f, "unexpected end of query"),
+            JsonPathErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            JsonPathErrorKind::InvalidNumber => write!(f, "invalid number"),
+            JsonPathErrorKind::UnknownFunction(name) => write!(f, "unknown function {}()", name),
+            JsonPathErrorKind::ArgumentCount(name) => {
+                write!(f, "wrong number of arguments to {}()", name)
+            }
+            JsonPathErrorKind::ArgumentType(name) => {
+                write!(f, "argument of the wrong type to {}()", name)
+            }
+            JsonPathErrorKind::NotSingular => {
+                write!(f, "comparison with a query that can select several nodes")
+            }
+            JsonPathErrorKind::NotComparable => {
+                write!(f, "comparison with a function that gives no value")
+            }
+            JsonPathErrorKind::NotATest => write!(f, "filter is not a test"),
+            JsonPathErrorKind::TooDeep => write!(f, "nesting too deep"),
+        }
+    }
+}
+
+/// A JSONPath syntax error along with the byte offset in the query it was found at.
+#[derive(Debug, PartialEq, Clone)]
+pub struct JsonPathError {
+    kind: JsonPathErrorKind,
+    offset: usize,
+}
+
+impl JsonPathError {
+    pub fn kind(&self) -> &JsonPathErrorKind {
+        &self.kind
+    }
+
+    /// Byte offset into the query.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+/// A node selected by a query, a reference to the value along with where it is.
+#[derive(Debug, PartialEq, Clone)]
+pub struct JsonPathNode<'v> {
+    path: Vec<PathElement>,
+    value: &'v JsonValue,
+}
+
+impl<'v> JsonPathNode<'v> {
+    pub fn value(&self) -> &'v JsonValue {
+        self.value
+    }
+
+    /// The names and indexes leading to the node from the root.
+    pub fn path(&self) -> &[PathElement] {
+        &self.path
+    }
+
+    /// The normalized path of the node, like `$['items'][0]`, which is a query that selects
+    /// just this node.
+    pub fn normalized_path(&self) -> String {
+        let mut path = String::from("$");
+        for element in &self.path {
+            match element {
+                PathElement::Name(name) => {
+                    path.push_str("['");
+                    escape_name(&mut path, name);
+                    path.push_str("']");
+                }
+                PathElement::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+
+    /// The JSON Pointer of the node, for `JsonValue::pointer` and the other pointer methods.
+    pub fn pointer(&self) -> String {
+        path_pointer(&self.path)
+    }
+}
+
+// Escapes a member name the way normalized paths write it
+fn escape_name(out: &mut String, name: &str) {
+    for ch in name.chars() {
+        match ch {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0C}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch < '\u{20}' => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+}
+
+/// A JSONPath query (RFC 9535), like `$.store.book[?@.price < 10].title`.
+///
+/// Parsing checks the whole query, including the types of function arguments, so a query
+/// that parses can be run on any value. Running it gives the selected nodes in the order
+/// RFC 9535 defines, with object members in the order of the `JsonMap`.
+///
+/// Supported are name, wildcard, index, slice and filter selectors, in child (`[...]`, `.name`)
+/// and descendant (`..[...]`, `..name`) segments. Filters compare with `==`, `!=`, `<`, `<=`,
+/// `>` and `>=`, combine tests with `&&`, `||` and `!`, and can call the functions `length`,
+/// `count`, `match`, `search` and `value`. Regular expressions follow I-Regexp (RFC 9485).
+///
+/// ```
+/// use json_reader::{parse_json_string, JsonPath};
+///
+/// let value = parse_json_string(r#"{"items": [{"id": 1, "price": 5}, {"id": 2, "price": 15}]}"#)?;
+/// let path = JsonPath::parse("$.items[?@.price < 10].id")?;
+///
+/// let nodes = path.query(&value);
+/// assert_eq!(1, nodes.len());
+/// assert_eq!("$['items'][0]['id']", nodes[0].normalized_path());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    query: String,
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath, JsonPathError> {
+        let mut parser = QueryParser {
+            query,
+            pos: 0,
+            depth: 0,
+        };
+
+        match parser.peek() {
+            Some('$') => parser.pos += 1,
+            _ => return Err(parser.unexpected()),
+        }
+        let segments = parser.parse_segments()?;
+        if parser.pos < query.len() {
+            return Err(parser.unexpected());
+        }
+
+        Ok(JsonPath {
+            query: String::from(query),
+            segments,
+        })
+    }
+
+    /// The nodes the query selects in `value`.
+    pub fn query<'v>(&self, value: &'v JsonValue) -> Vec<JsonPathNode<'v>> {
+        let root = vec![Node {
+            value,
+            location: Some(Location::Root),
+        }];
+
+        select(&self.segments, root, value)
+            .into_iter()
+            .map(|node| JsonPathNode {
+                path: node.location.map(Location::into_path).unwrap_or_default(),
+                value: node.value,
+            })
+            .collect()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.query
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(query)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.query)
+    }
+}
+
+impl JsonValue {
+    /// The nodes a JSONPath query selects in the value, see `JsonPath`.
+    pub fn query(&self, query: &str) -> Result<Vec<JsonPathNode<'_>>, JsonPathError> {
+        Ok(JsonPath::parse(query)?.query(self))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Expr),
+}
+
+// A query inside a filter, starting at `@` or `$`
+#[derive(Debug, Clone)]
+struct Query {
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    // Whether the query selects at most one node, whatever the value
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors[..],
+                    [Selector::Name(_)] | [Selector::Index(_)]
+                )
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Box<Operand>, CompareOp, Box<Operand>),
+    // A query is true when it selects anything
+    Exists(Query),
+    Test(Function),
+}
+
+// A literal, singular query or function that gives a value, or nothing
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(JsonValue),
+    Query(Query),
+    Function(Function),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// The types of RFC 9535's function extensions
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Type {
+    Value,
+    Logical,
+    Nodes,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum FunctionName {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+impl FunctionName {
+    fn from_name(name: &str) -> Option<FunctionName> {
+        match name {
+            "length" => Some(FunctionName::Length),
+            "count" => Some(FunctionName::Count),
+            "match" => Some(FunctionName::Match),
+            "search" => Some(FunctionName::Search),
+            "value" => Some(FunctionName::Value),
+            _ => None,
+        }
+    }
+
+    fn parameters(self) -> &'static [Type] {
+        match self {
+            FunctionName::Length => &[Type::Value],
+            FunctionName::Count | FunctionName::Value => &[Type::Nodes],
+            FunctionName::Match | FunctionName::Search => &[Type::Value, Type::Value],
+        }
+    }
+
+    fn result(self) -> Type {
+        match self {
+            FunctionName::Length | FunctionName::Count | FunctionName::Value => Type::Value,
+            FunctionName::Match | FunctionName::Search => Type::Logical,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: FunctionName,
+    arguments: Vec<Argument>,
+    // The pattern of match or search compiled ahead, when it is a literal. None for an
+    // invalid pattern, which never matches
+    regex: Option<Option<Regex>>,
+}
+
+#[derive(Debug, Clone)]
+enum Argument {
+    Value(Operand),
+    Nodes(Query),
+}
+
+// What an operand of && and || or a function argument was parsed as. Whether a lone operand
+// is valid depends on where it is
+enum Parsed {
+    Expr(Expr),
+    Operand(Operand),
+}
+
+struct QueryParser<'q> {
+    query: &'q str,
+    pos: usize,
+    depth: usize,
+}
+
+impl QueryParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.query[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = self.query[self.pos..].starts_with(expected);
+        if found {
+            self.pos += expected.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonPathError> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.pos += ch.len_utf8();
+                Ok(())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, kind: JsonPathErrorKind, offset: usize) -> JsonPathError {
+        JsonPathError { kind, offset }
+    }
+
+    fn unexpected(&self) -> JsonPathError {
+        match self.peek() {
+            Some(ch) => self.error(JsonPathErrorKind::UnexpectedCharacter(ch), self.pos),
+            None => self.error(JsonPathErrorKind::UnexpectedEnd, self.pos),
+        }
+    }
+
+    // Runs `parse` one level deeper for the expression at `start`, so that untrusted queries
+    // can't overflow the stack
+    fn nested<T>(
+        &mut self,
+        start: usize,
+        parse: impl FnOnce(&mut Self) -> Result<T, JsonPathError>,
+    ) -> Result<T, JsonPathError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(JsonPathErrorKind::TooDeep, start));
+        }
+
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+
+        parsed
+    }
+
+    // Segments may be separated by whitespace, which is left alone when no segment follows
+    fn parse_segments(&mut self) -> Result<Vec<Segment>, JsonPathError> {
+        let mut segments = Vec::new();
+
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            match self.peek() {
+                Some('.' | '[') => segments.push(self.parse_segment()?),
+                _ => {
+                    self.pos = before;
+                    return Ok(segments);
+                }
+            }
+        }
+    }
+
+    fn parse_segment(&mut self) -> Result<Segment, JsonPathError> {
+        let descendant = self.eat("..");
+        let selectors = if descendant || self.peek() == Some('[') {
+            match self.peek() {
+                Some('[') => self.parse_bracketed()?,
+                Some('*') => {
+                    self.pos += 1;
+                    vec![Selector::Wildcard]
+                }
+                _ => vec![Selector::Name(self.parse_member_name()?)],
+            }
+        } else {
+            self.pos += 1;
+            if self.eat("*") {
+                vec![Selector::Wildcard]
+            } else {
+                vec![Selector::Name(self.parse_member_name()?)]
+            }
+        };
+
+        Ok(Segment {
+            descendant,
+            selectors,
+        })
+    }
+
+    // The name in the `.name` shorthand
+    fn parse_member_name(&mut self) -> Result<String, JsonPathError> {
+        let start = self.pos;
+
+        match self.peek() {
+            Some(ch) if ch.is_ascii_alphabetic() || ch == '_' || !ch.is_ascii() => (),
+            _ => return Err(self.unexpected()),
+        }
+        while let Some(ch) = self.peek() {
+            if !(ch.is_ascii_alphanumeric() || ch == '_' || !ch.is_ascii()) {
+                break;
+            }
+            self.pos += ch.len_utf8();
+        }
+
+        Ok(String::from(&self.query[start..self.pos]))
+    }
+
+    fn parse_bracketed(&mut self) -> Result<Vec<Selector>, JsonPathError> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.parse_selector()?);
+            self.skip_whitespace();
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect(']')?;
+
+        Ok(selectors)
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, JsonPathError> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => Ok(Selector::Name(self.parse_string(quote)?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                let start = self.pos;
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.nested(start, Self::parse_logical)?))
+            }
+            Some('-' | '0'..='9' | ':') => self.parse_index_or_slice(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_index_or_slice(&mut self) -> Result<Selector, JsonPathError> {
+        let start = match self.peek() {
+            Some(':') => None,
+            _ => Some(self.parse_integer()?),
+        };
+
+        let before = self.pos;
+        self.skip_whitespace();
+        if !self.eat(":") {
+            self.pos = before;
+            // Only reached with a start, which an index is
+            return Ok(Selector::Index(start.unwrap_or_default()));
+        }
+
+        self.skip_whitespace();
+        let end = self.parse_optional_integer()?;
+        self.skip_whitespace();
+        let step = if self.eat(":") {
+            self.skip_whitespace();
+            self.parse_optional_integer()?
+        } else {
+            None
+        };
+
+        Ok(Selector::Slice { start, end, step })
+    }
+
+    fn parse_optional_integer(&mut self) -> Result<Option<i64>, JsonPathError> {
+        match self.peek() {
+            Some('-' | '0'..='9') => Ok(Some(self.parse_integer()?)),
+            _ => Ok(None),
+        }
+    }
+
+    // An integer without leading zeros, and without a minus sign on 0
+    fn parse_integer(&mut self) -> Result<i64, JsonPathError> {
+        let start = self.pos;
+        let negative = self.eat("-");
+
+        let digits_start = self.pos;
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        let digits = &self.query[digits_start..self.pos];
+
+        let invalid = self.error(JsonPathErrorKind::InvalidNumber, start);
+        match digits.as_bytes() {
+            [] => return Err(self.unexpected()),
+            [b'0'] if !negative => return Ok(0),
+            [b'0', ..] => return Err(invalid),
+            _ => (),
+        }
+
+        match digits.parse::<i64>() {
+            Ok(integer) if integer <= MAX_INTEGER => Ok(if negative { -integer } else { integer }),
+            _ => Err(invalid),
+        }
+    }
+
+    // A string literal in single or double quotes, with JSON's escapes plus \' in single
+    // quotes
+    fn parse_string(&mut self, quote: char) -> Result<String, JsonPathError> {
+        self.pos += 1;
+        let mut content = String::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.unexpected()),
+                Some(ch) if ch == quote => {
+                    self.pos += 1;
+                    return Ok(content);
+                }
+                Some('\\') => content.push(self.parse_escape(quote)?),
+                Some(ch) if ch < '\u{20}' => return Err(self.unexpected()),
+                Some(ch) => {
+                    content.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self, quote: char) -> Result<char, JsonPathError> {
+        let start = self.pos;
+        let invalid_escape = self.error(JsonPathErrorKind::InvalidEscape, start);
+        self.pos += 1;
+
+        let escaped = self.peek().ok_or_else(|| self.unexpected())?;
+        self.pos += escaped.len_utf8();
+        match escaped {
+            'b' => Ok('\u{08}'),
+            'f' => Ok('\u{0C}'),
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '/' | '\\' => Ok(escaped),
+            ch if ch == quote => Ok(ch),
+            'u' => {
+                let code_unit = self.parse_hex_code_unit().ok_or(invalid_escape.clone())?;
+                let code_point = match code_unit {
+                    0xD800..=0xDBFF => {
+                        if !self.eat("\\u") {
+                            return Err(invalid_escape);
+                        }
+                        match self.parse_hex_code_unit() {
+                            Some(low @ 0xDC00..=0xDFFF) => {
+                                0x10000 + ((code_unit - 0xD800) << 10) + (low - 0xDC00)
+                            }
+                            _ => return Err(invalid_escape),
+                        }
+                    }
+                    code_unit => code_unit,
+                };
+                char::from_u32(code_point).ok_or(invalid_escape)
+            }
+            _ => Err(invalid_escape),
+        }
+    }
+
+    fn parse_hex_code_unit(&mut self) -> Option<u32> {
+        let hex = self.query.get(self.pos..self.pos + 4)?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).ok()
+    }
+
+    // A filter's logical expression, which has to be a test
+    fn parse_logical(&mut self) -> Result<Expr, JsonPathError> {
+        let start = self.pos;
+        let parsed = self.parse_or()?;
+        self.test_expr(parsed, start)
+    }
+
+    fn parse_or(&mut self) -> Result<Parsed, JsonPathError> {
+        self.parse_chain("||", Self::parse_and, Expr::Or)
+    }
+
+    fn parse_and(&mut self) -> Result<Parsed, JsonPathError> {
+        self.parse_chain("&&", Self::parse_basic, Expr::And)
+    }
+
+    // Operands joined by `op`. The operands have to be tests when there is more than one
+    fn parse_chain(
+        &mut self,
+        op: &str,
+        parse_operand: fn(&mut Self) -> Result<Parsed, JsonPathError>,
+        combine: fn(Vec<Expr>) -> Expr,
+    ) -> Result<Parsed, JsonPathError> {
+        let start = self.pos;
+        let first = parse_operand(self)?;
+        let mut operands = Vec::new();
+
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            if !self.eat(op) {
+                self.pos = before;
+                break;
+            }
+            self.skip_whitespace();
+            let operand_start = self.pos;
+            let operand = parse_operand(self)?;
+            operands.push(self.test_expr(operand, operand_start)?);
+        }
+
+        if operands.is_empty() {
+            return Ok(first);
+        }
+        operands.insert(0, self.test_expr(first, start)?);
+        Ok(Parsed::Expr(combine(operands)))
+    }
+
+    fn parse_basic(&mut self) -> Result<Parsed, JsonPathError> {
+        if self.eat("!") {
+            self.skip_whitespace();
+            let start = self.pos;
+            let negated = match self.peek() {
+                Some('(') => self.nested(start, Self::parse_parenthesized)?,
+                _ => {
+                    let operand = self.parse_operand()?;
+                    self.test_expr(Parsed::Operand(operand), start)?
+                }
+            };
+            return Ok(Parsed::Expr(Expr::Not(Box::new(negated))));
+        }
+
+        if self.peek() == Some('(') {
+            let start = self.pos;
+            return Ok(Parsed::Expr(self.nested(start, Self::parse_parenthesized)?));
+        }
+
+        let left_start = self.pos;
+        let left = self.parse_operand()?;
+        let before = self.pos;
+        self.skip_whitespace();
+        let op = match self.parse_compare_op() {
+            Some(op) => op,
+            None => {
+                self.pos = before;
+                return Ok(Parsed::Operand(left));
+            }
+        };
+        self.skip_whitespace();
+        let right_start = self.pos;
+        let right = self.parse_operand()?;
+
+        Ok(Parsed::Expr(Expr::Compare(
+            Box::new(self.comparable(left, left_start)?),
+            op,
+            Box::new(self.comparable(right, right_start)?),
+        )))
+    }
+
+    fn parse_parenthesized(&mut self) -> Result<Expr, JsonPathError> {
+        self.expect('(')?;
+        self.skip_whitespace();
+        let expr = self.parse_logical()?;
+        self.skip_whitespace();
+        self.expect(')')?;
+        Ok(expr)
+    }
+
+    fn parse_compare_op(&mut self) -> Option<CompareOp> {
+        // The two-character operators go first, so that <= isn't read as <
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        ops.into_iter()
+            .find(|(text, _)| self.eat(text))
+            .map(|(_, op)| op)
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, JsonPathError> {
+        match self.peek() {
+            Some(root @ ('@' | '$')) => {
+                self.pos += 1;
+                Ok(Operand::Query(Query {
+                    absolute: root == '$',
+                    segments: self.parse_segments()?,
+                }))
+            }
+            Some(quote @ ('\'' | '"')) => Ok(Operand::Literal(JsonValue::JsonString(
+                self.parse_string(quote)?,
+            ))),
+            Some('-' | '0'..='9') => Ok(Operand::Literal(JsonValue::JsonNum(self.parse_number()?))),
+            Some('a'..='z') => {
+                let start = self.pos;
+                while let Some('a'..='z' | '0'..='9' | '_') = self.peek() {
+                    self.pos += 1;
+                }
+                let name = &self.query[start..self.pos];
+
+                match name {
+                    _ if self.peek() == Some('(') => {
+                        let function =
+                            self.nested(start, |parser| parser.parse_function(name, start))?;
+                        Ok(Operand::Function(function))
+                    }
+                    "true" => Ok(Operand::Literal(JsonValue::JsonBool(true))),
+                    "false" => Ok(Operand::Literal(JsonValue::JsonBool(false))),
+                    "null" => Ok(Operand::Literal(JsonValue::JsonNull)),
+                    _ => {
+                        self.pos = start;
+                        Err(self.unexpected())
+                    }
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // A number literal, as in JSON
+    fn parse_number(&mut self) -> Result<f64, JsonPathError> {
+        let start = self.pos;
+        let invalid = self.error(JsonPathErrorKind::InvalidNumber, start);
+        let skip_digits = |parser: &mut Self| {
+            let digits_start = parser.pos;
+            while let Some('0'..='9') = parser.peek() {
+                parser.pos += 1;
+            }
+            parser.pos - digits_start
+        };
+
+        self.eat("-");
+        let int_start = self.pos;
+        match skip_digits(self) {
+            0 => return Err(invalid),
+            1 => (),
+            _ if self.query[int_start..].starts_with('0') => return Err(invalid),
+            _ => (),
+        }
+        if self.eat(".") && skip_digits(self) == 0 {
+            return Err(invalid);
+        }
+        if self.eat("e") || self.eat("E") {
+            let _ = self.eat("+") || self.eat("-");
+            if skip_digits(self) == 0 {
+                return Err(invalid);
+            }
+        }
+
+        self.query[start..self.pos].parse().map_err(|_| invalid)
+    }
+
+    fn parse_function(&mut self, name: &str, start: usize) -> Result<Function, JsonPathError> {
+        let function = FunctionName::from_name(name).ok_or_else(|| {
+            self.error(
+                JsonPathErrorKind::UnknownFunction(String::from(name)),
+                start,
+            )
+        })?;
+        self.expect('(')?;
+        self.skip_whitespace();
+
+        let mut arguments = Vec::new();
+        if self.peek() != Some(')') {
+            loop {
+                let argument_start = self.pos;
+                arguments.push((self.parse_or()?, argument_start));
+                self.skip_whitespace();
+                if !self.eat(",") {
+                    break;
+                }
+                self.skip_whitespace();
+            }
+        }
+        self.skip_whitespace();
+        self.expect(')')?;
+
+        let parameters = function.parameters();
+        if arguments.len() != parameters.len() {
+            return Err(self.error(JsonPathErrorKind::ArgumentCount(String::from(name)), start));
+        }
+
+        let arguments = arguments
+            .into_iter()
+            .zip(parameters)
+            .map(|((argument, argument_start), parameter)| {
+                self.argument(argument, *parameter).ok_or_else(|| {
+                    self.error(
+                        JsonPathErrorKind::ArgumentType(String::from(name)),
+                        argument_start,
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let regex = match (function, &arguments[..]) {
+            (
+                FunctionName::Match | FunctionName::Search,
+                [_, Argument::Value(Operand::Literal(JsonValue::JsonString(pattern)))],
+            ) => Some(i_regexp(pattern, function == FunctionName::Match)),
+            _ => None,
+        };
+
+        Ok(Function {
+            name: function,
+            arguments,
+            regex,
+        })
+    }
+
+    // Converts an argument to the type of its parameter, None when it can't be
+    fn argument(&self, argument: Parsed, parameter: Type) -> Option<Argument> {
+        match (parameter, argument) {
+            (Type::Value, Parsed::Operand(Operand::Query(query))) if !query.is_singular() => None,
+            (Type::Value, Parsed::Operand(Operand::Function(function)))
+                if function.name.result() != Type::Value =>
+            {
+                None
+            }
+            (Type::Value, Parsed::Operand(operand)) => Some(Argument::Value(operand)),
+            (Type::Nodes, Parsed::Operand(Operand::Query(query))) => Some(Argument::Nodes(query)),
+            _ => None,
+        }
+    }
+
+    fn test_expr(&self, parsed: Parsed, start: usize) -> Result<Expr, JsonPathError> {
+        match parsed {
+            Parsed::Expr(expr) => Ok(expr),
+            Parsed::Operand(Operand::Query(query)) => Ok(Expr::Exists(query)),
+            Parsed::Operand(Operand::Function(function))
+                if function.name.result() == Type::Logical =>
+            {
+                Ok(Expr::Test(function))
+            }
+            Parsed::Operand(_) => Err(self.error(JsonPathErrorKind::NotATest, start)),
+        }
+    }
+
+    fn comparable(&self, operand: Operand, start: usize) -> Result<Operand, JsonPathError> {
+        match &operand {
+            Operand::Query(query) if !query.is_singular() => {
+                Err(self.error(JsonPathErrorKind::NotSingular, start))
+            }
+            Operand::Function(function) if function.name.result() != Type::Value => {
+                Err(self.error(JsonPathErrorKind::NotComparable, start))
+            }
+            _ => Ok(operand),
+        }
+    }
+}
+
+// Translates an I-Regexp to the regex crate's syntax. The two mostly agree, except that `.`
+// in I-Regexp matches anything but a line break. None for an invalid pattern
+fn i_regexp(pattern: &str, whole: bool) -> Option<Regex> {
+    let mut translated = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                translated.push(ch);
+                translated.push(chars.next()?);
+            }
+            '[' if !in_class => {
+                in_class = true;
+                translated.push(ch);
+            }
+            ']' if in_class => {
+                in_class = false;
+                translated.push(ch);
+            }
+            '.' if !in_class => translated.push_str("[^\\n\\r]"),
+            ch => translated.push(ch),
+        }
+    }
+
+    if whole {
+        translated = format!("\\A(?:{})\\z", translated);
+    }
+    Regex::new(&translated).ok()
+}
+
+// Where a node is, as a link to where its parent is
+enum Location {
+    Root,
+    Child(Rc<Location>, PathElement),
+}
+
+impl Location {
+    fn into_path(self) -> Vec<PathElement> {
+        let mut path = Vec::new();
+        let mut location = &self;
+        while let Location::Child(parent, element) = location {
+            path.push(element.clone());
+            location = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+// A selected value, with its location when the query result needs it
+struct Node<'v> {
+    value: &'v JsonValue,
+    location: Option<Location>,
+}
+
+impl<'v> Node<'v> {
+    fn child(parent: &Option<Rc<Location>>, element: PathElement, value: &'v JsonValue) -> Self {
+        Node {
+            value,
+            location: parent
+                .as_ref()
+                .map(|parent| Location::Child(Rc::clone(parent), element)),
+        }
+    }
+}
+
+fn select<'v>(
+    segments: &[Segment],
+    mut nodes: Vec<Node<'v>>,
+    root: &'v JsonValue,
+) -> Vec<Node<'v>> {
+    for segment in segments {
+        let mut selected = Vec::new();
+
+        for node in nodes {
+            let location = node.location.map(Rc::new);
+            if !segment.descendant {
+                apply_selectors(segment, node.value, &location, root, &mut selected);
+                continue;
+            }
+
+            // The node and all its descendants, parents before children and in the order
+            // of arrays and maps
+            let mut stack = vec![(node.value, location)];
+            while let Some((value, location)) = stack.pop() {
+                apply_selectors(segment, value, &location, root, &mut selected);
+
+                let children = child_nodes(value, &location);
+                stack.extend(
+                    children
+                        .into_iter()
+                        .rev()
+                        .map(|child| (child.value, child.location.map(Rc::new))),
+                );
+            }
+        }
+
+        nodes = selected;
+    }
+
+    nodes
+}
+
+fn child_nodes<'v>(value: &'v JsonValue, location: &Option<Rc<Location>>) -> Vec<Node<'v>> {
+    match value {
+        JsonValue::JsonObj(map) => map
+            .iter()
+            .map(|(key, value)| Node::child(location, PathElement::Name(key.clone()), value))
+            .collect(),
+        JsonValue::JsonArr(vector) => vector
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Node::child(location, PathElement::Index(i), value))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn apply_selectors<'v>(
+    segment: &Segment,
+    value: &'v JsonValue,
+    location: &Option<Rc<Location>>,
+    root: &'v JsonValue,
+    selected: &mut Vec<Node<'v>>,
+) {
+    for selector in &segment.selectors {
+        match (selector, value) {
+            (Selector::Name(name), JsonValue::JsonObj(map)) => {
+                if let Some(member) = map.get(name) {
+                    selected.push(Node::child(
+                        location,
+                        PathElement::Name(name.clone()),
+                        member,
+                    ));
+                }
+            }
+            (Selector::Wildcard, _) => selected.extend(child_nodes(value, location)),
+            (Selector::Index(index), JsonValue::JsonArr(vector)) => {
+                if let Some(i) = normalize_index(*index, vector.len()) {
+                    selected.push(Node::child(location, PathElement::Index(i), &vector[i]));
+                }
+            }
+            (Selector::Slice { start, end, step }, JsonValue::JsonArr(vector)) => {
+                for i in slice_indexes(*start, *end, *step, vector.len()) {
+                    selected.push(Node::child(location, PathElement::Index(i), &vector[i]));
+                }
+            }
+            (Selector::Filter(expr), _) => selected.extend(
+                child_nodes(value, location)
+                    .into_iter()
+                    .filter(|child| test(expr, child.value, root)),
+            ),
+            _ => (),
+        }
+    }
+}
+
+// The index an index selector refers to, counting from the end when negative
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let index = if index < 0 { len + index } else { index };
+    (0..len).contains(&index).then_some(index as usize)
+}
+
+// The indexes a slice selects, following RFC 9535 section 2.3.4.2.2
+fn slice_indexes(
+    start: Option<i64>,
+    end: Option<i64>,
+    step: Option<i64>,
+    len: usize,
+) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+
+    let mut indexes = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indexes.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indexes.push(i as usize);
+            i += step;
+        }
+    }
+    indexes
+}
+
+// The values a query inside a filter selects
+fn query_values<'a>(
+    query: &'a Query,
+    current: &'a JsonValue,
+    root: &'a JsonValue,
+) -> Vec<&'a JsonValue> {
+    let start = if query.absolute { root } else { current };
+    let nodes = vec![Node {
+        value: start,
+        location: None,
+    }];
+
+    select(&query.segments, nodes, root)
+        .into_iter()
+        .map(|node| node.value)
+        .collect()
+}
+
+fn test(expr: &Expr, current: &JsonValue, root: &JsonValue) -> bool {
+    match expr {
+        Expr::Or(exprs) => exprs.iter().any(|expr| test(expr, current, root)),
+        Expr::And(exprs) => exprs.iter().all(|expr| test(expr, current, root)),
+        Expr::Not(expr) => !test(expr, current, root),
+        Expr::Compare(left, op, right) => {
+            let left = operand_value(left, current, root);
+            let right = operand_value(right, current, root);
+            compare(left.as_deref(), *op, right.as_deref())
+        }
+        Expr::Exists(query) => !query_values(query, current, root).is_empty(),
+        Expr::Test(function) => call_logical(function, current, root),
+    }
+}
+
+// The value of a comparison operand or function argument, None for Nothing
+fn operand_value<'a>(
+    operand: &'a Operand,
+    current: &'a JsonValue,
+    root: &'a JsonValue,
+) -> Option<Cow<'a, JsonValue>> {
+    match operand {
+        Operand::Literal(value) => Some(Cow::Borrowed(value)),
+        Operand::Query(query) => query_values(query, current, root)
+            .first()
+            .map(|value| Cow::Borrowed(*value)),
+        Operand::Function(function) => call_value(function, current, root),
+    }
+}
+
+fn compare(left: Option<&JsonValue>, op: CompareOp, right: Option<&JsonValue>) -> bool {
+    let equal = left == right;
+    let less = |left: Option<&JsonValue>, right: Option<&JsonValue>| match (left, right) {
+        (Some(JsonValue::JsonNum(left)), Some(JsonValue::JsonNum(right))) => left < right,
+        // Strings are ordered by code point, which is the order of their UTF-8 bytes
+        (Some(JsonValue::JsonString(left)), Some(JsonValue::JsonString(right))) => left < right,
+        _ => false,
+    };
+
+    match op {
+        CompareOp::Eq => equal,
+        CompareOp::Ne => !equal,
+        CompareOp::Lt => less(left, right),
+        CompareOp::Le => less(left, right) || equal,
+        CompareOp::Gt => less(right, left),
+        CompareOp::Ge => less(right, left) || equal,
+    }
+}
+
+fn argument_value<'a>(
+    argument: &'a Argument,
+    current: &'a JsonValue,
+    root: &'a JsonValue,
+) -> Option<Cow<'a, JsonValue>> {
+    match argument {
+        Argument::Value(operand) => operand_value(operand, current, root),
+        _ => None,
+    }
+}
+
+fn argument_nodes<'a>(
+    argument: &'a Argument,
+    current: &'a JsonValue,
+    root: &'a JsonValue,
+) -> Vec<&'a JsonValue> {
+    match argument {
+        Argument::Nodes(query) => query_values(query, current, root),
+        _ => Vec::new(),
+    }
+}
+
+// Calls a function whose result is a value
+fn call_value<'a>(
+    function: &'a Function,
+    current: &'a JsonValue,
+    root: &'a JsonValue,
+) -> Option<Cow<'a, JsonValue>> {
+    let arguments = &function.arguments;
+
+    match function.name {
+        FunctionName::Length => {
+            let len = match argument_value(&arguments[0], current, root)?.as_ref() {
+                JsonValue::JsonString(str) => str.chars().count(),
+                JsonValue::JsonArr(vector) => vector.len(),
+                JsonValue::JsonObj(map) => map.len(),
+                _ => return None,
+            };
+            Some(Cow::Owned(JsonValue::JsonNum(len as f64)))
+        }
+        FunctionName::Count => {
+            let count = argument_nodes(&arguments[0], current, root).len();
+            Some(Cow::Owned(JsonValue::JsonNum(count as f64)))
+        }
+        FunctionName::Value => match argument_nodes(&arguments[0], current, root)[..] {
+            [value] => Some(Cow::Borrowed(value)),
+            _ => None,
+        },
+        FunctionName::Match | FunctionName::Search => None,
+    }
+}
+
+// Calls a function whose result is true or false
+fn call_logical(function: &Function, current: &JsonValue, root: &JsonValue) -> bool {
+    let arguments = &function.arguments;
+
+    match function.name {
+        FunctionName::Match | FunctionName::Search => {
+            let text = argument_value(&arguments[0], current, root);
+            let Some(JsonValue::JsonString(text)) = text.as_deref() else {
+                return false;
+            };
+
+            let compiled;
+            let regex = match &function.regex {
+                Some(regex) => regex.as_ref(),
+                None => {
+                    let pattern = argument_value(&arguments[1], current, root);
+                    let Some(JsonValue::JsonString(pattern)) = pattern.as_deref() else {
+                        return false;
+                    };
+                    compiled = i_regexp(pattern, function.name == FunctionName::Match);
+                    compiled.as_ref()
+                }
+            };
+
+            regex.is_some_and(|regex| regex.is_match(text))
+        }
+        FunctionName::Length | FunctionName::Count | FunctionName::Value => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json_string;
+
+    fn json(json_string: &str) -> JsonValue {
+        parse_json_string(json_string).unwrap()
+    }
+
+    fn bookstore() -> JsonValue {
+        json(
+            r#"{"store": {
+                "book": [
+                    {"category": "reference", "author": "Nigel Rees",
+                     "title": "Sayings of the Century", "price": 8.95},
+                    {"category": "fiction", "author": "Evelyn Waugh",
+                     "title": "Sword of Honour", "price": 12.99},
+                    {"category": "fiction", "author": "Herman Melville",
+                     "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99},
+                    {"category": "fiction", "author": "J. R. R. Tolkien",
+                     "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99}
+                ],
+                "bicycle": {"color": "red", "price": 399}
+            }}"#,
+        )
+    }
+
+    fn paths(value: &JsonValue, query: &str) -> Vec<String> {
+        value
+            .query(query)
+            .unwrap()
+            .iter()
+            .map(JsonPathNode::normalized_path)
+            .collect()
+    }
+
+    #[test]
+    fn runs_the_rfc_examples() {
+        let store = bookstore();
+        let authors: Vec<&str> = store
+            .query("$.store.book[*].author")
+            .unwrap()
+            .iter()
+            .map(|node| node.value().as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "Nigel Rees",
+                "Evelyn Waugh",
+                "Herman Melville",
+                "J. R. R. Tolkien"
+            ],
+            authors
+        );
+        assert_eq!(4, store.query("$..author").unwrap().len());
+        assert_eq!(
+            vec!["$['store']['book']", "$['store']['bicycle']"],
+            paths(&store, "$.store.*")
+        );
+        assert_eq!(
+            vec![
+                "$['store']['book'][0]['price']",
+                "$['store']['book'][1]['price']",
+                "$['store']['book'][2]['price']",
+                "$['store']['book'][3]['price']",
+                "$['store']['bicycle']['price']"
+            ],
+            paths(&store, "$.store..price")
+        );
+        assert_eq!(vec!["$['store']['book'][2]"], paths(&store, "$..book[2]"));
+        assert_eq!(vec!["$['store']['book'][3]"], paths(&store, "$..book[-1]"));
+        assert_eq!(
+            vec!["$['store']['book'][0]", "$['store']['book'][1]"],
+            paths(&store, "$..book[:2]")
+        );
+        assert_eq!(
+            vec!["$['store']['book'][2]", "$['store']['book'][3]"],
+            paths(&store, "$..book[?@.isbn]")
+        );
+        assert_eq!(
+            vec!["$['store']['book'][0]", "$['store']['book'][2]"],
+            paths(&store, "$..book[?@.price<10]")
+        );
+        assert_eq!(27, store.query("$..*").unwrap().len());
+    }
+
+    #[test]
+    fn selects_slices_in_either_direction() {
+        let letters = json(r#"["a", "b", "c", "d", "e", "f", "g"]"#);
+        let values = |query: &str| -> Vec<String> {
+            letters
+                .query(query)
+                .unwrap()
+                .iter()
+                .map(|node| String::from(node.value().as_str().unwrap()))
+                .collect()
+        };
+
+        assert_eq!(vec!["b", "c"], values("$[1:3]"));
+        assert_eq!(vec!["f", "g"], values("$[5:]"));
+        assert_eq!(vec!["b", "d"], values("$[1:5:2]"));
+        assert_eq!(vec!["f", "d"], values("$[5:1:-2]"));
+        assert_eq!(vec!["g", "f", "e", "d", "c", "b", "a"], values("$[::-1]"));
+        assert_eq!(Vec::<String>::new(), values("$[::0]"));
+        assert_eq!(vec!["a", "g"], values("$[0, -1]"));
+    }
+
+    #[test]
+    fn filters_with_functions_and_logic() {
+        let value = json(
+            r#"[
+                {"name": "alpha", "tags": ["x", "y"], "n": 1},
+                {"name": "beta", "tags": [], "n": 2},
+                {"name": "gamma", "n": "2"},
+                {"name": "délta", "tags": ["x"]}
+            ]"#,
+        );
+        let names = |query: &str| -> Vec<String> {
+            value
+                .query(query)
+                .unwrap()
+                .iter()
+                .map(|node| String::from(node.value().pointer("/name").unwrap().as_str().unwrap()))
+                .collect()
+        };
+
+        assert_eq!(vec!["alpha", "délta"], names("$[?length(@.tags) > 0]"));
+        assert_eq!(vec!["gamma", "délta"], names("$[?count(@.*) == 2]"));
+        assert_eq!(
+            vec!["alpha", "gamma"],
+            names("$[?match(@.name, '[ag].*a')]")
+        );
+        assert_eq!(vec!["délta"], names("$[?search(@.name, 'é')]"));
+        assert_eq!(vec!["délta"], names("$[?length(@.name) == 5 && !@.n]"));
+        assert_eq!(vec!["alpha", "beta"], names("$[?@.n == 1 || @.n == 2]"));
+        assert_eq!(vec!["beta"], names("$[?!(@.n < 2) && @.n && @.n != '2']"));
+        assert_eq!(vec!["alpha", "délta"], names("$[?value(@.tags[0]) == 'x']"));
+        assert_eq!(vec!["gamma"], names("$[?@.tags == @.missing]"));
+        assert_eq!(vec!["alpha"], names("$[?@.n == $[0].n]"));
+    }
+
+    #[test]
+    fn writes_normalized_paths_and_pointers() {
+        let value = json(r#"{"a'b": {"c/d": [0, {"\u0001\n": 1}]}}"#);
+        let nodes = value.query("$..*[?@ == 1]").unwrap();
+
+        assert_eq!(1, nodes.len());
+        assert_eq!(
+            r"$['a\'b']['c/d'][1]['\u0001\n']",
+            nodes[0].normalized_path()
+        );
+        assert_eq!("/a'b/c~1d/1/\u{1}\n", nodes[0].pointer());
+        assert_eq!(Ok(nodes[0].value()), value.pointer(&nodes[0].pointer()));
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        let cases = [
+            ("", JsonPathErrorKind::UnexpectedEnd, 0),
+            ("$ ", JsonPathErrorKind::UnexpectedCharacter(' '), 1),
+            ("$[01]", JsonPathErrorKind::InvalidNumber, 2),
+            ("$[-0]", JsonPathErrorKind::InvalidNumber, 2),
+            ("$[9007199254740992]", JsonPathErrorKind::InvalidNumber, 2),
+            ("$['\\x']", JsonPathErrorKind::InvalidEscape, 3),
+            (
+                "$[?foo(@)]",
+                JsonPathErrorKind::UnknownFunction(String::from("foo")),
+                3,
+            ),
+            (
+                "$[?length(@, 1)]",
+                JsonPathErrorKind::ArgumentCount(String::from("length")),
+                3,
+            ),
+            (
+                "$[?length(@.*) > 1]",
+                JsonPathErrorKind::ArgumentType(String::from("length")),
+                10,
+            ),
+            (
+                "$[?count(1) > 1]",
+                JsonPathErrorKind::ArgumentType(String::from("count")),
+                9,
+            ),
+            ("$[?@.* == 1]", JsonPathErrorKind::NotSingular, 3),
+            (
+                "$[?match(@.a, 'x') == true]",
+                JsonPathErrorKind::NotComparable,
+                3,
+            ),
+            ("$[?length(@.a)]", JsonPathErrorKind::NotATest, 3),
+            ("$[?1]", JsonPathErrorKind::NotATest, 3),
+            ("$[?@.a == 1 && 2]", JsonPathErrorKind::NotATest, 15),
+        ];
+        // Nesting that would overflow the stack is rejected at the 129th level, counting the filter
+        let parentheses = format!("$[?{}@.a{}]", "(".repeat(200_000), ")".repeat(200_000));
+        let filters = format!("${}", "[?@".repeat(200));
+        let functions = format!("$[?{}@.a{}]", "length(".repeat(200), ")".repeat(200));
+        let cases = cases.into_iter().chain([
+            (&parentheses[..], JsonPathErrorKind::TooDeep, 3 + 127),
+            (&filters[..], JsonPathErrorKind::TooDeep, 2 + 128 * 3),
+            (&functions[..], JsonPathErrorKind::TooDeep, 3 + 127 * 7),
+        ]);
+
+        for (query, kind, offset) in cases {
+            let error = JsonPath::parse(query).unwrap_err();
+            assert_eq!((&kind, offset), (error.kind(), error.offset()), "{}", query);
+        }
+    }
+}
@@ -1,485 +1,804 @@
-use std::{collections::HashMap, fmt};
+use std::{fmt, io};
+
+mod diff;
+#[cfg(feature = "jsonpath")]
+mod jsonpath;
+mod map;
+mod merge_patch;
+mod ndjson;
+mod options;
+mod parser;
+mod patch;
+mod pointer;
+mod ser;
+mod stream;
+mod value;
+
+pub use diff::{ArrayMatching, DiffOptions, JsonChange, JsonDiff};
+#[cfg(feature = "jsonpath")]
+pub use jsonpath::{JsonPath, JsonPathError, JsonPathErrorKind, JsonPathNode};
+pub use map::JsonMap;
+pub use ndjson::{NdjsonReader, NdjsonWriter};
+pub use options::{DuplicateKeys, ParserOptions};
+use parser::Parser;
+pub use patch::{JsonPatch, PatchError, PatchErrorKind, PatchOperation};
+pub use pointer::{PathElement, PointerError, PointerErrorKind};
+pub use ser::{Indent, Newline, PrettyConfig};
+pub use stream::{JsonEvent, JsonEventReader, JsonPushParser, Progress};
+pub use value::JsonValue;
 
 #[derive(Debug, Clone)]
 pub struct InvalidJsonUnwrap;
 
 impl fmt::Display for InvalidJsonUnwrap {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Invalid unwrap of JsonToken")
+        write!(f, "Invalid unwrap of JsonValue")
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct InvalidJson;
+/// The reason a JSON document failed to parse.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum JsonErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    UnterminatedString,
+    InvalidEscape,
+    LoneSurrogate,
+    ControlCharacter,
+    InvalidNumber,
+    /// A number too large for an `f64`, which would be infinite, at its first character.
+    NumberOutOfRange,
+    InvalidLiteral,
+    ExpectedKey,
+    ExpectedValue,
+    MissingColon,
+    MissingComma,
+    UnclosedContainer,
+    TrailingComma,
+    TrailingData,
+    NotAnObject,
+    /// Nesting deeper than `ParserOptions::max_depth`, at the first container too deep.
+    TooDeep,
+    /// A string or key longer than `ParserOptions::max_string_bytes`, at its opening quote.
+    StringTooLong,
+    /// An array or object with more than `ParserOptions::max_members`, at its opening bracket.
+    TooManyMembers,
+    /// Input longer than `ParserOptions::max_input_bytes`, at the start of the input.
+    InputTooLarge,
+    /// An object key seen before, rejected by `DuplicateKeys::Error`. The error points at the
+    /// repeated key, these fields at its first occurrence.
+    DuplicateKey {
+        key: String,
+        first_offset: usize,
+        first_line: usize,
+        first_column: usize,
+    },
+    /// Bytes that are not UTF-8, at the first of them.
+    InvalidUtf8,
+    /// Reading the input failed, at the position reached so far.
+    Io(io::ErrorKind),
+}
 
-impl fmt::Display for InvalidJson {
+impl fmt::Display for JsonErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Json string has invalid syntax")
+        match self {
+            JsonErrorKind::UnexpectedCharacter(ch) => write!(f, "unexpected character {:?}", ch),
+            JsonErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            JsonErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            JsonErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            JsonErrorKind::LoneSurrogate => write!(f, "lone UTF-16 surrogate in escape"),
+            JsonErrorKind::ControlCharacter => write!(f, "unescaped control character in string"),
+            JsonErrorKind::InvalidNumber => write!(f, "invalid number"),
+            JsonErrorKind::NumberOutOfRange => write!(f, "number out of range"),
+            JsonErrorKind::InvalidLiteral => write!(f, "invalid literal"),
+            JsonErrorKind::ExpectedKey => write!(f, "expected an object key"),
+            JsonErrorKind::ExpectedValue => write!(f, "expected a value"),
+            JsonErrorKind::MissingColon => write!(f, "missing colon after object key"),
+            JsonErrorKind::MissingComma => write!(f, "missing comma between elements"),
+            JsonErrorKind::UnclosedContainer => write!(f, "unclosed object or array"),
+            JsonErrorKind::TrailingComma => write!(f, "trailing comma"),
+            JsonErrorKind::TrailingData => write!(f, "trailing data after the document"),
+            JsonErrorKind::NotAnObject => write!(f, "top-level value is not an object"),
+            JsonErrorKind::TooDeep => write!(f, "nesting too deep"),
+            JsonErrorKind::StringTooLong => write!(f, "string too long"),
+            JsonErrorKind::TooManyMembers => write!(f, "too many array elements or object members"),
+            JsonErrorKind::InputTooLarge => write!(f, "input too large"),
+            JsonErrorKind::DuplicateKey {
+                key,
+                first_line,
+                first_column,
+                ..
+            } => write!(
+                f,
+                "duplicate key {:?} (first seen at line {} column {})",
+                key, first_line, first_column
+            ),
+            JsonErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            JsonErrorKind::Io(kind) => write!(f, "I/O error: {}", kind),
+        }
     }
 }
 
+/// A parse error along with the position in the input it was found at.
 #[derive(Debug, PartialEq, Clone)]
-pub enum JsonToken {
-    JsonKey(String),
-    JsonString(String),
-    JsonNum(f64),
-    JsonBool(bool),
-    JsonObj(HashMap<String, JsonToken>),
-    JsonArr(Vec<JsonToken>),
-    JsonArrBeg,
-    JsonArrEnd,
-    JsonObjBeg,
-    JsonObjEnd,
-    JsonInvalid,
+pub struct JsonError {
+    kind: JsonErrorKind,
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
-impl JsonToken {
-    pub fn is_key(&self) -> bool {
-        match self {
-            JsonToken::JsonKey(_) => {
-                return true;
-            }
-            _ => {
-                return false;
-            }
-        }
+impl JsonError {
+    pub(crate) fn new(kind: JsonErrorKind, json_string: &str, offset: usize) -> JsonError {
+        JsonError::at(kind, TextPosition::START.advance(&json_string[..offset]))
     }
 
-    pub fn is_value(&self) -> bool {
-        match self {
-            JsonToken::JsonKey(_)
-            | JsonToken::JsonArrBeg
-            | JsonToken::JsonArrEnd
-            | JsonToken::JsonObjBeg
-            | JsonToken::JsonObjEnd
-            | JsonToken::JsonInvalid => false,
-            _ => true,
+    pub(crate) fn at(kind: JsonErrorKind, position: TextPosition) -> JsonError {
+        JsonError {
+            kind,
+            offset: position.offset,
+            line: position.line,
+            column: position.column,
         }
     }
 
-    pub fn as_str(&self) -> Result<&str, InvalidJsonUnwrap> {
-        match self {
-            JsonToken::JsonString(str) => Ok(str as &str),
-            _ => Err(InvalidJsonUnwrap),
-        }
+    pub fn kind(&self) -> &JsonErrorKind {
+        &self.kind
     }
 
-    pub fn as_f64(&self) -> Result<f64, InvalidJsonUnwrap> {
-        match self {
-            JsonToken::JsonNum(num) => Ok(*num),
-            _ => Err(InvalidJsonUnwrap),
-        }
+    /// Byte offset into the input.
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
-    pub fn as_bool(&self) -> bool {
-        match self {
-            JsonToken::JsonBool(bin) => *bin,
-            _ => unreachable!(),
-        }
+    /// Line number, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
     }
 
-    pub fn as_map(&self) -> Result<HashMap<String, JsonToken>, InvalidJsonUnwrap> {
-        match self {
-            JsonToken::JsonObj(map) => Ok(map.clone()),
-            _ => Err(InvalidJsonUnwrap),
-        }
+    /// Column on the line in characters, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
     }
+}
 
-    pub fn as_vec(&self) -> Result<Vec<JsonToken>, InvalidJsonUnwrap> {
-        match self {
-            JsonToken::JsonArr(vector) => Ok(vector.clone()),
-            _ => Err(InvalidJsonUnwrap),
+// A byte offset with its 1-based line and column in characters, for the parsers that only see
+// part of the input at a time and can't work the line out from the offset later
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct TextPosition {
+    pub(crate) offset: usize,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+impl TextPosition {
+    pub(crate) const START: TextPosition = TextPosition {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    // The position right after `text`, which starts at this position
+    pub(crate) fn advance(self, text: &str) -> TextPosition {
+        let offset = self.offset + text.len();
+
+        match text.rfind('\n') {
+            Some(last) => TextPosition {
+                offset,
+                line: self.line + text.bytes().filter(|b| *b == b'\n').count(),
+                column: text[last + 1..].chars().count() + 1,
+            },
+            None => TextPosition {
+                offset,
+                line: self.line,
+                column: self.column + text.chars().count(),
+            },
         }
     }
 }
 
-fn tokenize_json_string(json_string: &String) -> Vec<JsonToken> {
-    let mut char_inds = json_string.char_indices().peekable();
-    let mut tokens: Vec<JsonToken> = Vec::new();
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
 
-    while let Some((_pos, ch)) = char_inds.next() {
-        match ch {
-            // Object parsing
-            '{' => {
-                tokens.push(JsonToken::JsonObjBeg);
-            }
-            '}' => {
-                tokens.push(JsonToken::JsonObjEnd);
-            }
-            // Array parsing
-            '[' => {
-                tokens.push(JsonToken::JsonArrBeg);
-            }
-            ']' => {
-                tokens.push(JsonToken::JsonArrEnd);
-            }
-            // String parsing
-            '"' => {
-                let mut last_matched: char = ch;
-                let str_content: String = char_inds
-                    .by_ref()
-                    .take_while(|(_pos, c)| {
-                        if *c != '"' || last_matched == '\\' {
-                            last_matched = *c;
-                            return true;
-                        }
-                        false
-                    })
-                    .map(|(_pos, c)| c)
-                    .collect();
-
-                if let Some((_pos, ch)) = char_inds.peek() {
-                    if *ch == ':' {
-                        tokens.push(JsonToken::JsonKey(str_content.replace("\\", "")));
-                        continue;
-                    } else if *ch == ',' || *ch == '{' || *ch == '}' || *ch == '[' || *ch == ']' {
-                        tokens.push(JsonToken::JsonString(str_content.replace("\\", "")));
-                        continue;
-                    }
-                }
+impl std::error::Error for JsonError {}
 
-                while let Some((_pos, _ch)) = char_inds.next() {
-                    match char_inds.peek() {
-                        Some((_pos, c)) => {
-                            if *c == ':' {
-                                tokens.push(JsonToken::JsonKey(str_content.replace("\\", "")));
-                                break;
-                            } else if *c == ',' || *c == '{' || *c == '}' || *c == '[' || *c == ']'
-                            {
-                                tokens.push(JsonToken::JsonString(str_content.replace("\\", "")));
-                                break;
-                            }
-                        }
-                        None => (),
-                    }
-                }
-            }
+/// A lexical token produced by `tokenize_json_string`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
+    JsonKey(String),
+    JsonString(String),
+    JsonNum(f64),
+    JsonBool(bool),
+    JsonNull,
+    JsonArrBeg,
+    JsonArrEnd,
+    JsonObjBeg,
+    JsonObjEnd,
+    JsonComma,
+    JsonColon,
+}
 
-            // Number parsing
-            c if c.is_numeric() => {
-                let mut number: String = String::from(c);
-                while let Some((_pos, ch)) = char_inds.next() {
-                    if ch.is_numeric() || ch == '.' {
-                        number.push(ch);
-                    }
+impl Token {
+    pub fn is_key(&self) -> bool {
+        matches!(self, Token::JsonKey(_))
+    }
 
-                    match char_inds.peek() {
-                        Some((_pos, c)) => {
-                            if !c.is_numeric() {
-                                break;
-                            }
-                        }
-                        None => (),
-                    }
-                }
+    /// Whether the token is a scalar value, i.e. a string, number, boolean or null.
+    pub fn is_value(&self) -> bool {
+        matches!(
+            self,
+            Token::JsonString(_) | Token::JsonNum(_) | Token::JsonBool(_) | Token::JsonNull
+        )
+    }
 
-                tokens.push(JsonToken::JsonNum(number.parse::<f64>().unwrap()));
-            }
-            // Boolean parsing
-            c if c.is_alphabetic() => {
-                let mut value: String = String::from(c);
-
-                while let Some((_pos, ch)) = char_inds.next() {
-                    value.push(ch);
-
-                    match char_inds.peek() {
-                        Some((_pos, c)) => {
-                            if c.is_ascii_punctuation() || *c == ' ' {
-                                break;
-                            }
-                        }
-                        None => (),
-                    }
-                }
+    pub fn is_separator(&self) -> bool {
+        matches!(self, Token::JsonComma | Token::JsonColon)
+    }
 
-                let truth = String::from("true");
-                let falth = String::from("false");
+    /// The value of a scalar token, see `is_value`.
+    pub fn to_value(&self) -> Option<JsonValue> {
+        JsonValue::try_from(self.clone()).ok()
+    }
+}
 
-                if value == truth {
-                    tokens.push(JsonToken::JsonBool(true));
-                }
-                if value == falth {
-                    tokens.push(JsonToken::JsonBool(false));
-                }
-            }
-            _ => (),
-        }
+/// Parsed documents used to be made of the same type as the tokens, they are now `JsonValue`s
+/// whose variants keep the old names.
+#[deprecated(note = "use `JsonValue` for parsed documents and `Token` for the tokenizer")]
+pub type JsonToken = JsonValue;
+
+/// Splits a JSON document into its tokens, without checking that they are in a valid order.
+pub fn tokenize_json_string(json_string: &str) -> Result<Vec<Token>, JsonError> {
+    Ok(tokenize_json_positions(json_string)?
+        .into_iter()
+        .map(|(_pos, token)| token)
+        .collect())
+}
+
+// Tokenizes the input, pairing every token with the byte offset it starts at
+fn tokenize_json_positions(json_string: &str) -> Result<Vec<(usize, Token)>, JsonError> {
+    let mut parser = Parser::new(json_string);
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+
+    while let Some(token) = parser.next_token()? {
+        tokens.push(token);
     }
 
-    return tokens;
+    Ok(tokens)
+}
+
+// What the validator accepts next
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Expect {
+    // Any value, or the end of the array that was just opened
+    FirstValueOrClose,
+    // Any value, at the top level or after a colon
+    Value,
+    // A value after a comma in an array
+    NextValue,
+    // A key, or the end of the object that was just opened
+    FirstKeyOrClose,
+    // A key after a comma in an object
+    Key,
+    Colon,
+    CommaOrClose,
+    // Nothing, the top-level value is complete
+    End,
 }
 
-fn check_is_valid_json(json_token_vec: &Vec<JsonToken>) -> bool {
-    let mut num_arr_tokens: usize = 0;
-    let mut num_obj_tokens: usize = 0;
-    let mut num_vals: usize = 0;
-    let mut num_keys: usize = 0;
-    let iter = json_token_vec.iter();
+// Checks the tokens against the JSON grammar: containers close in the order they were opened,
+// elements are separated by commas, keys are followed by colons, and there are no trailing
+// commas or extra values after the document
+fn check_is_valid_json(
+    json_string: &str,
+    json_token_vec: &[(usize, Token)],
+) -> Result<(), JsonError> {
+    // Opening tokens of the containers that are not closed yet, along with their positions
+    let mut open: Vec<(usize, &Token)> = Vec::new();
+    let mut expect = Expect::Value;
+    let max_depth = ParserOptions::default().max_depth;
+
+    for (pos, token) in json_token_vec {
+        let error = |kind| Err(JsonError::new(kind, json_string, *pos));
+
+        expect = match (expect, token) {
+            (Expect::End, _) => return error(JsonErrorKind::TrailingData),
+
+            // Closing tokens, which have to match the innermost open container
+            (
+                Expect::FirstValueOrClose | Expect::FirstKeyOrClose | Expect::CommaOrClose,
+                Token::JsonArrEnd | Token::JsonObjEnd,
+            ) => {
+                let matches_open = match open.last() {
+                    Some((_pos, Token::JsonArrBeg)) => *token == Token::JsonArrEnd,
+                    Some((_pos, Token::JsonObjBeg)) => *token == Token::JsonObjEnd,
+                    _ => false,
+                };
+
+                if !matches_open {
+                    return error(JsonErrorKind::UnexpectedCharacter(closing_char(token)));
+                }
 
-    for token in iter {
-        match token {
-            JsonToken::JsonKey(_) => {
-                num_keys += 1;
+                open.pop();
+                after_value(&open)
             }
-            JsonToken::JsonString(_) => {
-                num_vals += 1;
+            (Expect::Value, Token::JsonArrEnd | Token::JsonObjEnd) => {
+                if open.is_empty() {
+                    return error(JsonErrorKind::UnexpectedCharacter(closing_char(token)));
+                }
+                return error(JsonErrorKind::ExpectedValue);
             }
-            JsonToken::JsonNum(_) => {
-                num_vals += 1;
+            (Expect::NextValue | Expect::Key, Token::JsonArrEnd | Token::JsonObjEnd) => {
+                return error(JsonErrorKind::TrailingComma)
             }
-            JsonToken::JsonBool(_) => {
-                num_vals += 1;
+
+            // Keys, a string that is not followed by a colon is still taken as the key so
+            // the missing colon gets reported
+            (Expect::FirstKeyOrClose | Expect::Key, Token::JsonKey(_) | Token::JsonString(_)) => {
+                Expect::Colon
             }
-            JsonToken::JsonArrBeg => {
-                num_arr_tokens += 1;
+            (Expect::FirstKeyOrClose | Expect::Key, _) => return error(JsonErrorKind::ExpectedKey),
+            (Expect::Colon, Token::JsonColon) => Expect::Value,
+            (Expect::Colon, _) => return error(JsonErrorKind::MissingColon),
+
+            // Values, a key in value position is a string whose colon is reported next
+            (
+                Expect::FirstValueOrClose | Expect::Value | Expect::NextValue,
+                Token::JsonArrBeg | Token::JsonObjBeg,
+            ) if open.len() >= max_depth => return error(JsonErrorKind::TooDeep),
+            (Expect::FirstValueOrClose | Expect::Value | Expect::NextValue, Token::JsonArrBeg) => {
+                open.push((*pos, token));
+                Expect::FirstValueOrClose
             }
-            JsonToken::JsonArrEnd => {
-                num_arr_tokens += 1;
+            (Expect::FirstValueOrClose | Expect::Value | Expect::NextValue, Token::JsonObjBeg) => {
+                open.push((*pos, token));
+                Expect::FirstKeyOrClose
             }
-            JsonToken::JsonObjBeg => {
-                num_obj_tokens += 1;
+            (Expect::FirstValueOrClose | Expect::Value | Expect::NextValue, Token::JsonKey(_)) => {
+                after_value(&open)
             }
-            JsonToken::JsonObjEnd => {
-                num_obj_tokens += 1;
+            (Expect::FirstValueOrClose | Expect::Value | Expect::NextValue, token)
+                if token.is_value() =>
+            {
+                after_value(&open)
             }
-            JsonToken::JsonInvalid => {
-                return false;
+            (Expect::FirstValueOrClose | Expect::Value | Expect::NextValue, _) => {
+                return error(JsonErrorKind::ExpectedValue)
             }
-            _ => (),
-        }
+
+            // Separators between elements
+            (Expect::CommaOrClose, Token::JsonComma) => match open.last() {
+                Some((_pos, Token::JsonObjBeg)) => Expect::Key,
+                _ => Expect::NextValue,
+            },
+            (Expect::CommaOrClose, Token::JsonColon) => {
+                return error(JsonErrorKind::UnexpectedCharacter(':'))
+            }
+            (Expect::CommaOrClose, _) => return error(JsonErrorKind::MissingComma),
+        };
     }
 
-    // If either of these two values are not even, that means that there is an unclosed object or array
-    if num_arr_tokens % 2 != 0 || num_obj_tokens % 2 != 0 {
-        return false;
+    if let Some((pos, _token)) = open.last() {
+        return Err(JsonError::new(
+            JsonErrorKind::UnclosedContainer,
+            json_string,
+            *pos,
+        ));
     }
 
-    // If the number of keys is not greater than the number of values, arrays, and objects, then there is a key without a value (not fool proof, but better than nothing)
-    if num_keys > num_vals + (num_obj_tokens - 2) + num_arr_tokens {
-        return false;
+    if expect != Expect::End {
+        return Err(JsonError::new(
+            JsonErrorKind::UnexpectedEnd,
+            json_string,
+            json_string.len(),
+        ));
     }
 
-    return true;
+    Ok(())
 }
 
-fn from_tokens_to_datastructure(json_tokens: &Vec<JsonToken>) -> JsonToken {
-    let start_ind = json_tokens
-        .iter()
-        .rposition(|e| *e == JsonToken::JsonArrBeg || *e == JsonToken::JsonObjBeg)
-        .unwrap();
+// What comes after a complete value depends on whether it is nested in a container
+fn after_value(open: &[(usize, &Token)]) -> Expect {
+    if open.is_empty() {
+        Expect::End
+    } else {
+        Expect::CommaOrClose
+    }
+}
 
-    if json_tokens[start_ind] == JsonToken::JsonArrBeg {
-        let j_vec: Vec<JsonToken> = json_tokens
-            .iter()
-            .skip(start_ind + 1)
-            .take_while(|e| **e != JsonToken::JsonArrEnd)
-            .map(|e| e.clone())
-            .collect();
+fn closing_char(token: &Token) -> char {
+    if *token == Token::JsonArrEnd {
+        ']'
+    } else {
+        '}'
+    }
+}
+
+// While folding, the token list holds both tokens and the containers already built from them
+#[derive(Debug, PartialEq, Clone)]
+enum Folded {
+    Token(Token),
+    Value(JsonValue),
+}
+
+impl From<Token> for Folded {
+    fn from(token: Token) -> Self {
+        match JsonValue::try_from(token) {
+            Ok(value) => Folded::Value(value),
+            Err(token) => Folded::Token(token),
+        }
+    }
+}
 
-        JsonToken::JsonArr(j_vec)
+// Builds the array or object spanned by `json_tokens`, which starts with an opening token and
+// ends with the first closing token after it, so every nested container is already folded
+fn from_tokens_to_datastructure(
+    json_string: &str,
+    json_tokens: &[(usize, Folded)],
+) -> Result<JsonValue, JsonError> {
+    let (close_pos, close) = &json_tokens[json_tokens.len() - 1];
+    let contents = &json_tokens[1..json_tokens.len() - 1];
+
+    let is_array = json_tokens[0].1 == Folded::Token(Token::JsonArrBeg);
+    let expected_close = if is_array {
+        Token::JsonArrEnd
     } else {
-        let j_vec: Vec<JsonToken> = json_tokens
-            .iter()
-            .skip(start_ind + 1)
-            .take_while(|e| **e != JsonToken::JsonObjEnd)
-            .map(|e| e.clone())
-            .collect();
+        Token::JsonObjEnd
+    };
 
-        let mut j_iter = j_vec.iter();
-        let mut j_map: HashMap<String, JsonToken> = HashMap::new();
+    if *close != Folded::Token(expected_close) {
+        let ch = if *close == Folded::Token(Token::JsonArrEnd) {
+            ']'
+        } else {
+            '}'
+        };
+        return Err(JsonError::new(
+            JsonErrorKind::UnexpectedCharacter(ch),
+            json_string,
+            *close_pos,
+        ));
+    }
 
-        while let Some(item) = j_iter.next() {
-            let value = j_iter.next().unwrap();
+    if is_array {
+        let mut j_vec: Vec<JsonValue> = Vec::new();
 
+        for (pos, item) in contents {
             match item {
-                JsonToken::JsonKey(key) => {
+                Folded::Value(value) => j_vec.push(value.clone()),
+                Folded::Token(_) => {
+                    return Err(JsonError::new(
+                        JsonErrorKind::ExpectedValue,
+                        json_string,
+                        *pos,
+                    ))
+                }
+            }
+        }
+
+        Ok(JsonValue::JsonArr(j_vec))
+    } else {
+        let mut j_iter = contents.iter();
+        let mut j_map: JsonMap = JsonMap::new();
+
+        while let Some((key_pos, item)) = j_iter.next() {
+            let key = match item {
+                Folded::Token(Token::JsonKey(key)) => key,
+                _ => {
+                    return Err(JsonError::new(
+                        JsonErrorKind::ExpectedKey,
+                        json_string,
+                        *key_pos,
+                    ))
+                }
+            };
+
+            match j_iter.next() {
+                Some((_pos, Folded::Value(value))) => {
                     j_map.insert(key.clone(), value.clone());
                 }
-                _ => (),
+                Some((pos, Folded::Token(_))) => {
+                    return Err(JsonError::new(
+                        JsonErrorKind::ExpectedValue,
+                        json_string,
+                        *pos,
+                    ))
+                }
+                None => {
+                    return Err(JsonError::new(
+                        JsonErrorKind::ExpectedValue,
+                        json_string,
+                        *close_pos,
+                    ))
+                }
             }
         }
 
-        JsonToken::JsonObj(j_map)
+        Ok(JsonValue::JsonObj(j_map))
     }
 }
 
-fn flatten_json_tokens(mut json_tokens: Vec<JsonToken>) -> HashMap<String, JsonToken> {
-    while let Some(start) = json_tokens
-        .iter()
-        .rposition(|e| *e == JsonToken::JsonArrBeg || *e == JsonToken::JsonObjBeg)
-    {
+fn flatten_json_tokens(
+    json_string: &str,
+    mut json_tokens: Vec<(usize, Folded)>,
+) -> Result<JsonValue, JsonError> {
+    let is_opening = |e: &Folded| {
+        *e == Folded::Token(Token::JsonArrBeg) || *e == Folded::Token(Token::JsonObjBeg)
+    };
+    let is_closing = |e: &Folded| {
+        *e == Folded::Token(Token::JsonArrEnd) || *e == Folded::Token(Token::JsonObjEnd)
+    };
+
+    while let Some(start) = json_tokens.iter().rposition(|(_pos, e)| is_opening(e)) {
         let end = json_tokens
             .iter()
             .skip(start)
-            .position(|e| *e == JsonToken::JsonArrEnd || *e == JsonToken::JsonObjEnd)
-            .unwrap();
-
-        let new_token = from_tokens_to_datastructure(&json_tokens);
+            .position(|(_pos, e)| is_closing(e))
+            .ok_or_else(|| {
+                JsonError::new(
+                    JsonErrorKind::UnclosedContainer,
+                    json_string,
+                    json_tokens[start].0,
+                )
+            })?;
 
         // Here, start + end is used because, using skip with the iterator above causes
         // the result of position to be in relation to the skip start. So, if one had a vector
         // [1, 2, 3, 4, 5, 6, 7, 8, 9] and used vec.iter().skip(1) and used position() to find
         // 9, the result would be 7, instead of 8.
+        let new_value =
+            from_tokens_to_datastructure(json_string, &json_tokens[start..=(start + end)])?;
+
+        let start_pos = json_tokens[start].0;
         json_tokens.drain(start..=(start + end));
-        json_tokens.insert(start, new_token);
+        json_tokens.insert(start, (start_pos, Folded::Value(new_value)));
     }
 
-    json_tokens[0].as_map().unwrap()
-}
-
-pub fn from_json_string(json_string: &String) -> Result<HashMap<String, JsonToken>, InvalidJson> {
-    let token_vec = tokenize_json_string(&json_string);
-
-    if !check_is_valid_json(&token_vec) {
-        return Err(InvalidJson);
+    // Once every container has been folded, a document is left with exactly one value
+    if let Some((pos, _token)) = json_tokens.get(1) {
+        return Err(JsonError::new(
+            JsonErrorKind::TrailingData,
+            json_string,
+            *pos,
+        ));
     }
 
-    Ok(flatten_json_tokens(token_vec))
+    match json_tokens.pop() {
+        Some((_pos, Folded::Value(value))) => Ok(value),
+        Some((pos, Folded::Token(Token::JsonArrEnd))) => Err(JsonError::new(
+            JsonErrorKind::UnexpectedCharacter(']'),
+            json_string,
+            pos,
+        )),
+        Some((pos, Folded::Token(Token::JsonObjEnd))) => Err(JsonError::new(
+            JsonErrorKind::UnexpectedCharacter('}'),
+            json_string,
+            pos,
+        )),
+        Some((pos, Folded::Token(_))) => Err(JsonError::new(
+            JsonErrorKind::ExpectedValue,
+            json_string,
+            pos,
+        )),
+        None => Err(JsonError::new(
+            JsonErrorKind::UnexpectedEnd,
+            json_string,
+            json_string.len(),
+        )),
+    }
 }
 
-fn json_tokens_to_json_array(json_token_vec: &Vec<JsonToken>) -> JsonToken {
-    let mut new_vec: Vec<JsonToken> = Vec::new();
-
-    let mut arr_inds: Vec<usize> = Vec::new();
-    let mut obj_inds: Vec<usize> = Vec::new();
+/// Parses a JSON document whose top-level value can be of any type, e.g. an array or a bare
+/// string or number.
+pub fn parse_json_string(json_string: &str) -> Result<JsonValue, JsonError> {
+    Parser::new(json_string).parse_document()
+}
 
-    json_token_vec.iter().enumerate().for_each(|(ind, val)| {
-        if *val == JsonToken::JsonArrBeg || *val == JsonToken::JsonArrEnd {
-            arr_inds.push(ind);
-        } else if *val == JsonToken::JsonObjBeg || *val == JsonToken::JsonObjEnd {
-            obj_inds.push(ind);
-        }
-    });
+/// Parses a JSON document like `parse_json_string`, with the behavior set by `options`.
+pub fn parse_json_string_with(
+    json_string: &str,
+    options: &ParserOptions,
+) -> Result<JsonValue, JsonError> {
+    Parser::with_options(json_string, options.clone()).parse_document()
+}
 
-    arr_inds.sort();
-    obj_inds.sort();
+/// Parses a JSON document from UTF-8 bytes, like `parse_json_string`. Bytes that are not
+/// UTF-8 are an `InvalidUtf8` error, unless the document is already invalid before them.
+pub fn parse_json_slice(json_bytes: &[u8]) -> Result<JsonValue, JsonError> {
+    parse_json_slice_with(json_bytes, &ParserOptions::default())
+}
 
-    let mut nesting = 0;
+/// Parses a JSON document from UTF-8 bytes, with the behavior set by `options`.
+pub fn parse_json_slice_with(
+    json_bytes: &[u8],
+    options: &ParserOptions,
+) -> Result<JsonValue, JsonError> {
+    parser::parse_bytes(json_bytes, TextPosition::START, options)
+}
 
-    for i in arr_inds[0]..arr_inds[arr_inds.len() - 1] {
-        if i == 0 || i == arr_inds[arr_inds.len() - 1] {
-            continue;
-        }
+/// Parses a JSON document from a reader, like `parse_json_slice`. The input is read in chunks
+/// as the parser needs it, so the reader may be read past the end of the document. To read
+/// several documents from one stream, use an `NdjsonReader`, or read the input and split it
+/// with `parse_json_prefix`.
+///
+/// Errors and their positions are the same as for the whole input parsed at once. The one
+/// difference is `max_input_bytes`, which is checked as the input comes in, so an error in
+/// the first part of a document too large is reported instead of `InputTooLarge`. A failed
+/// read is an `Io` error, reads interrupted by a signal are retried.
+///
+/// Wrapping the reader in a `BufReader` does not help, it is already read in large chunks.
+pub fn parse_json_reader<R: io::Read>(reader: R) -> Result<JsonValue, JsonError> {
+    parse_json_reader_with(reader, &ParserOptions::default())
+}
 
-        if json_token_vec[i].is_value() && nesting <= 0 {
-            new_vec.push(json_token_vec[i].clone());
-        } else {
-            match json_token_vec[i] {
-                JsonToken::JsonArrBeg => {
-                    new_vec.push(json_tokens_to_json_array(
-                        &json_token_vec[arr_inds[1]..=arr_inds[arr_inds.len() - 2]].to_vec(),
-                    ));
-                    nesting += 1;
-                }
-                JsonToken::JsonObjBeg => {
-                    new_vec.push(json_tokens_to_json_object(
-                        &json_token_vec[obj_inds[0]..=obj_inds[obj_inds.len() - 1]].to_vec(),
-                    ));
-                    nesting += 1;
-                }
-                JsonToken::JsonArrEnd => {
-                    nesting -= 1;
-                }
-                JsonToken::JsonObjEnd => {
-                    nesting -= 1;
-                }
-                _ => (),
-            }
-        }
-    }
+/// Parses a JSON document from a reader, with the behavior set by `options`.
+pub fn parse_json_reader_with<R: io::Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<JsonValue, JsonError> {
+    stream::parse_reader(reader, options)
+}
 
-    return JsonToken::JsonArr(new_vec);
+/// Parses one JSON value from the start of `json_string` and returns it with the rest of the
+/// input, for JSON embedded in other text. Leading whitespace is skipped, and the rest starts
+/// right after the value.
+///
+/// A value has to end where a JSON token could follow, so `[1]x` gives `[1]` and `"x"`, while
+/// `123abc` or `truex` are an error.
+pub fn parse_json_prefix(json_string: &str) -> Result<(JsonValue, &str), JsonError> {
+    Parser::new(json_string).parse_prefix()
 }
 
-pub fn json_tokens_to_json_object(json_token_vec: &Vec<JsonToken>) -> JsonToken {
-    let mut new_map: HashMap<String, JsonToken> = HashMap::new();
+/// The previous parser, which tokenizes the whole input and folds the tokens into containers
+/// one at a time. Only kept as a baseline for the benchmarks. Nesting is limited as by the
+/// default `ParserOptions`.
+#[doc(hidden)]
+pub fn parse_json_string_by_tokens(json_string: &str) -> Result<JsonValue, JsonError> {
+    let token_vec = tokenize_json_positions(json_string)?;
 
-    let mut arr_inds: Vec<usize> = Vec::new();
-    let mut obj_inds: Vec<usize> = Vec::new();
+    check_is_valid_json(json_string, &token_vec)?;
 
-    json_token_vec.iter().enumerate().for_each(|(ind, val)| {
-        if *val == JsonToken::JsonArrBeg || *val == JsonToken::JsonArrEnd {
-            arr_inds.push(ind);
-        } else if *val == JsonToken::JsonObjBeg || *val == JsonToken::JsonObjEnd {
-            obj_inds.push(ind);
-        }
-    });
+    // The separators have been checked, the tree is built from the remaining tokens
+    let folded_vec = token_vec
+        .into_iter()
+        .filter(|(_pos, token)| !token.is_separator())
+        .map(|(pos, token)| (pos, Folded::from(token)))
+        .collect();
 
-    arr_inds.sort();
-    obj_inds.sort();
+    flatten_json_tokens(json_string, folded_vec)
+}
 
-    let mut nesting = 0;
+/// Parses a JSON document whose top-level value is an object. Documents with any other
+/// top-level value are rejected, use `parse_json_string` for those.
+pub fn from_json_string(json_string: &str) -> Result<JsonMap, JsonError> {
+    match parse_json_string(json_string)? {
+        JsonValue::JsonObj(map) => Ok(map),
+        _ => Err(JsonError::new(
+            JsonErrorKind::NotAnObject,
+            json_string,
+            json_string.len() - json_string.trim_start().len(),
+        )),
+    }
+}
 
-    for i in obj_inds[0]..obj_inds[obj_inds.len() - 1] {
-        if i == 0 || i == obj_inds[obj_inds.len() - 1] {
-            continue;
-        }
+/// Builds the object described by a token list, like the one `tokenize_json_string` returns.
+/// Separators are optional, a key is taken to be followed by its value.
+///
+/// Tokens carry no position, so an invalid list is reported with the error kind alone. Nesting
+/// deeper than the default `ParserOptions::max_depth` is `TooDeep`.
+pub fn json_tokens_to_json_object(json_token_vec: &[Token]) -> Result<JsonValue, JsonErrorKind> {
+    match json_tokens_to_value(json_token_vec)? {
+        JsonValue::JsonObj(map) => Ok(JsonValue::JsonObj(map)),
+        _ => Err(JsonErrorKind::NotAnObject),
+    }
+}
 
-        let mut key = String::new();
-        if let JsonToken::JsonKey(val) = &json_token_vec[i] {
-            key = val.clone();
+// Folds the tokens with a stack of the containers still open, each with the key it goes under
+// in its parent, so deep nesting costs no recursion
+fn json_tokens_to_value(json_token_vec: &[Token]) -> Result<JsonValue, JsonErrorKind> {
+    let mut open: Vec<(Option<String>, JsonValue)> = Vec::new();
+    let mut key: Option<String> = None;
+    let mut document: Option<JsonValue> = None;
+    let max_depth = ParserOptions::default().max_depth;
+
+    for token in json_token_vec.iter().filter(|token| !token.is_separator()) {
+        if document.is_some() {
+            return Err(JsonErrorKind::TrailingData);
         }
 
-        if json_token_vec[i].is_key() && nesting <= 0 {
-            if json_token_vec[i + 1].is_value() {
-                new_map.insert(key.clone(), json_token_vec[i + 1].clone());
-            } else {
-                match json_token_vec[i + 1] {
-                    JsonToken::JsonArrBeg => {
-                        new_map.insert(
-                            key.clone(),
-                            json_tokens_to_json_array(
-                                &json_token_vec[arr_inds[0]..=arr_inds[arr_inds.len() - 1]]
-                                    .to_vec(),
-                            ),
-                        );
-                        nesting += 1;
-                    }
-                    JsonToken::JsonObjBeg => {
-                        new_map.insert(
-                            key.clone(),
-                            json_tokens_to_json_object(
-                                &json_token_vec[obj_inds[1]..=obj_inds[obj_inds.len() - 2]]
-                                    .to_vec(),
-                            ),
-                        );
-                        nesting += 1;
-                    }
-                    _ => (),
+        let (slot, value) = match token {
+            Token::JsonKey(name) => {
+                if key.is_some() || !matches!(open.last(), Some((_, JsonValue::JsonObj(_)))) {
+                    return Err(JsonErrorKind::ExpectedValue);
                 }
+                key = Some(name.clone());
+                continue;
             }
-        } else if nesting > 0 {
-            match json_token_vec[i] {
-                JsonToken::JsonArrEnd => {
-                    nesting -= 1;
+            Token::JsonArrBeg | Token::JsonObjBeg => {
+                let slot = value_slot(&open, &mut key)?;
+                if open.len() >= max_depth {
+                    return Err(JsonErrorKind::TooDeep);
                 }
-                JsonToken::JsonObjEnd => {
-                    nesting -= 1;
+                let container = if *token == Token::JsonArrBeg {
+                    JsonValue::JsonArr(Vec::new())
+                } else {
+                    JsonValue::JsonObj(JsonMap::new())
+                };
+                open.push((slot, container));
+                continue;
+            }
+            Token::JsonArrEnd | Token::JsonObjEnd => {
+                let is_array = *token == Token::JsonArrEnd;
+                match open.pop() {
+                    Some((slot, container @ JsonValue::JsonArr(_))) if is_array => {
+                        (slot, container)
+                    }
+                    Some((slot, container @ JsonValue::JsonObj(_))) if !is_array => {
+                        if key.is_some() {
+                            return Err(JsonErrorKind::ExpectedValue);
+                        }
+                        (slot, container)
+                    }
+                    _ => return Err(JsonErrorKind::UnexpectedCharacter(closing_char(token))),
                 }
-                _ => (),
             }
+            token => match token.to_value() {
+                Some(value) => (value_slot(&open, &mut key)?, value),
+                None => return Err(JsonErrorKind::ExpectedValue),
+            },
+        };
+
+        match (open.last_mut(), slot) {
+            (Some((_, JsonValue::JsonObj(map))), Some(slot)) => {
+                map.insert(slot, value);
+            }
+            (Some((_, JsonValue::JsonArr(vector))), None) => vector.push(value),
+            (None, None) => document = Some(value),
+            _ => return Err(JsonErrorKind::ExpectedKey),
         }
     }
 
-    return JsonToken::JsonObj(new_map);
+    if !open.is_empty() {
+        return Err(JsonErrorKind::UnclosedContainer);
+    }
+
+    document.ok_or(JsonErrorKind::UnexpectedEnd)
+}
+
+// The key a value about to start goes under, None inside arrays and at the top level
+fn value_slot(
+    open: &[(Option<String>, JsonValue)],
+    key: &mut Option<String>,
+) -> Result<Option<String>, JsonErrorKind> {
+    match open.last() {
+        Some((_, JsonValue::JsonObj(_))) => key.take().map(Some).ok_or(JsonErrorKind::ExpectedKey),
+        _ => Ok(None),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use proptest::prelude::*;
 
     #[test]
     fn reads_basic_json_string_w_key() {
         let json_string: String = String::from(r#"{"string": "Hello, World!"}"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("string")),
-                JsonToken::JsonString(String::from("Hello, World!")),
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("string")),
+                Token::JsonColon,
+                Token::JsonString(String::from("Hello, World!")),
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -488,12 +807,13 @@ mod tests {
         let json_string: String = String::from(r#"{"string": "Hell\"o, World!"}"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("string")),
-                JsonToken::JsonString(String::from("Hell\"o, World!")),
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("string")),
+                Token::JsonColon,
+                Token::JsonString(String::from("Hell\"o, World!")),
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -502,12 +822,13 @@ mod tests {
         let json_string: String = String::from(r#"{"number": 123}"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("number")),
-                JsonToken::JsonNum(123.0),
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("number")),
+                Token::JsonColon,
+                Token::JsonNum(123.0),
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -516,12 +837,13 @@ mod tests {
         let json_string: String = String::from(r#"{"boolean": true}"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("boolean")),
-                JsonToken::JsonBool(true),
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("boolean")),
+                Token::JsonColon,
+                Token::JsonBool(true),
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -530,14 +852,15 @@ mod tests {
         let json_string: String = String::from(r#"{"key":[ true ]}"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("key")),
-                JsonToken::JsonArrBeg,
-                JsonToken::JsonBool(true),
-                JsonToken::JsonArrEnd,
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("key")),
+                Token::JsonColon,
+                Token::JsonArrBeg,
+                Token::JsonBool(true),
+                Token::JsonArrEnd,
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -553,25 +876,34 @@ mod tests {
         );
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("hello")),
-                JsonToken::JsonString(String::from("world")),
-                JsonToken::JsonKey(String::from("bruh")),
-                JsonToken::JsonBool(true),
-                JsonToken::JsonKey(String::from("arr")),
-                JsonToken::JsonArrBeg,
-                JsonToken::JsonString(String::from("true")),
-                JsonToken::JsonBool(true),
-                JsonToken::JsonNum(123.0),
-                JsonToken::JsonArrEnd,
-                JsonToken::JsonKey(String::from("obj")),
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonString(String::from("hello")),
-                JsonToken::JsonNum(123.0),
-                JsonToken::JsonObjEnd,
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("hello")),
+                Token::JsonColon,
+                Token::JsonString(String::from("world")),
+                Token::JsonComma,
+                Token::JsonKey(String::from("bruh")),
+                Token::JsonColon,
+                Token::JsonBool(true),
+                Token::JsonComma,
+                Token::JsonKey(String::from("arr")),
+                Token::JsonColon,
+                Token::JsonArrBeg,
+                Token::JsonString(String::from("true")),
+                Token::JsonComma,
+                Token::JsonBool(true),
+                Token::JsonComma,
+                Token::JsonNum(123.0),
+                Token::JsonArrEnd,
+                Token::JsonKey(String::from("obj")),
+                Token::JsonColon,
+                Token::JsonObjBeg,
+                Token::JsonString(String::from("hello")),
+                Token::JsonComma,
+                Token::JsonNum(123.0),
+                Token::JsonObjEnd,
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -580,15 +912,17 @@ mod tests {
         let json_string = String::from(r#"{"object": {"hello": "world"}}"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("object")),
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("hello")),
-                JsonToken::JsonString(String::from("world")),
-                JsonToken::JsonObjEnd,
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("object")),
+                Token::JsonColon,
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("hello")),
+                Token::JsonColon,
+                Token::JsonString(String::from("world")),
+                Token::JsonObjEnd,
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -598,20 +932,25 @@ mod tests {
             String::from(r#"{ "nested-array": "does it work?", "arr": [123, [321, true]] }"#);
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("nested-array")),
-                JsonToken::JsonString(String::from("does it work?")),
-                JsonToken::JsonKey(String::from("arr")),
-                JsonToken::JsonArrBeg,
-                JsonToken::JsonNum(123.0),
-                JsonToken::JsonArrBeg,
-                JsonToken::JsonNum(321.0),
-                JsonToken::JsonBool(true),
-                JsonToken::JsonArrEnd,
-                JsonToken::JsonArrEnd,
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("nested-array")),
+                Token::JsonColon,
+                Token::JsonString(String::from("does it work?")),
+                Token::JsonComma,
+                Token::JsonKey(String::from("arr")),
+                Token::JsonColon,
+                Token::JsonArrBeg,
+                Token::JsonNum(123.0),
+                Token::JsonComma,
+                Token::JsonArrBeg,
+                Token::JsonNum(321.0),
+                Token::JsonComma,
+                Token::JsonBool(true),
+                Token::JsonArrEnd,
+                Token::JsonArrEnd,
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
@@ -622,78 +961,86 @@ mod tests {
         );
         assert_eq!(
             vec![
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("key1")),
-                JsonToken::JsonString(String::from("Hello, World!")),
-                JsonToken::JsonKey(String::from("key2")),
-                JsonToken::JsonArrBeg,
-                JsonToken::JsonNum(123.0),
-                JsonToken::JsonBool(true),
-                JsonToken::JsonArrBeg,
-                JsonToken::JsonString(String::from("I am nested!")),
-                JsonToken::JsonArrEnd,
-                JsonToken::JsonArrEnd,
-                JsonToken::JsonKey(String::from("key3")),
-                JsonToken::JsonObjBeg,
-                JsonToken::JsonKey(String::from("nested-obj")),
-                JsonToken::JsonString(String::from("Hello up there!")),
-                JsonToken::JsonObjEnd,
-                JsonToken::JsonObjEnd
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("key1")),
+                Token::JsonColon,
+                Token::JsonString(String::from("Hello, World!")),
+                Token::JsonComma,
+                Token::JsonKey(String::from("key2")),
+                Token::JsonColon,
+                Token::JsonArrBeg,
+                Token::JsonNum(123.0),
+                Token::JsonComma,
+                Token::JsonBool(true),
+                Token::JsonComma,
+                Token::JsonArrBeg,
+                Token::JsonString(String::from("I am nested!")),
+                Token::JsonArrEnd,
+                Token::JsonArrEnd,
+                Token::JsonComma,
+                Token::JsonKey(String::from("key3")),
+                Token::JsonColon,
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("nested-obj")),
+                Token::JsonColon,
+                Token::JsonString(String::from("Hello up there!")),
+                Token::JsonObjEnd,
+                Token::JsonObjEnd
             ],
-            tokenize_json_string(&json_string)
+            tokenize_json_string(&json_string).unwrap()
         );
     }
 
     #[test]
     fn detects_key() {
-        let key = JsonToken::JsonKey(String::from("This is a key!"));
-        assert_eq!(true, key.is_key());
+        let key = Token::JsonKey(String::from("This is a key!"));
+        assert!(key.is_key());
     }
 
     #[test]
     fn converts_tokens_to_hashmap_containers_following_each_other() {
-        let json_token_vec: Vec<JsonToken> = vec![
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("key1")),
-            JsonToken::JsonString(String::from("string")),
-            JsonToken::JsonKey(String::from("key2")),
-            JsonToken::JsonBool(true),
-            JsonToken::JsonKey(String::from("key3")),
-            JsonToken::JsonArrBeg,
-            JsonToken::JsonString(String::from("thing1")),
-            JsonToken::JsonString(String::from("thing2")),
-            JsonToken::JsonArrEnd,
-            JsonToken::JsonKey(String::from("key4")),
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("key1")),
-            JsonToken::JsonString(String::from("full test")),
-            JsonToken::JsonObjEnd,
-            JsonToken::JsonObjEnd,
+        let json_token_vec: Vec<Token> = vec![
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("key1")),
+            Token::JsonString(String::from("string")),
+            Token::JsonKey(String::from("key2")),
+            Token::JsonBool(true),
+            Token::JsonKey(String::from("key3")),
+            Token::JsonArrBeg,
+            Token::JsonString(String::from("thing1")),
+            Token::JsonString(String::from("thing2")),
+            Token::JsonArrEnd,
+            Token::JsonKey(String::from("key4")),
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("key1")),
+            Token::JsonString(String::from("full test")),
+            Token::JsonObjEnd,
+            Token::JsonObjEnd,
         ];
 
-        let mut json_map: HashMap<String, JsonToken> = HashMap::new();
-        let mut inner_map: HashMap<String, JsonToken> = HashMap::new();
+        let mut json_map: JsonMap = JsonMap::new();
+        let mut inner_map: JsonMap = JsonMap::new();
         inner_map.insert(
             String::from("key1"),
-            JsonToken::JsonString(String::from("full test")),
+            JsonValue::JsonString(String::from("full test")),
         );
         json_map.insert(
             String::from("key1"),
-            JsonToken::JsonString(String::from("string")),
+            JsonValue::JsonString(String::from("string")),
         );
-        json_map.insert(String::from("key2"), JsonToken::JsonBool(true));
+        json_map.insert(String::from("key2"), JsonValue::JsonBool(true));
         json_map.insert(
             String::from("key3"),
-            JsonToken::JsonArr(vec![
-                JsonToken::JsonString(String::from("thing1")),
-                JsonToken::JsonString(String::from("thing2")),
+            JsonValue::JsonArr(vec![
+                JsonValue::JsonString(String::from("thing1")),
+                JsonValue::JsonString(String::from("thing2")),
             ]),
         );
-        json_map.insert(String::from("key4"), JsonToken::JsonObj(inner_map));
+        json_map.insert(String::from("key4"), JsonValue::JsonObj(inner_map));
 
         let created_map = match json_tokens_to_json_object(&json_token_vec) {
-            JsonToken::JsonObj(map) => map,
-            _ => HashMap::new(),
+            Ok(JsonValue::JsonObj(map)) => map,
+            _ => JsonMap::new(),
         };
 
         assert_eq!(json_map, created_map);
@@ -701,28 +1048,28 @@ mod tests {
 
     #[test]
     fn converts_not_nested_tokens_to_hashmap() {
-        let json_token_vec: Vec<JsonToken> = vec![
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("hello")),
-            JsonToken::JsonString(String::from("world")),
-            JsonToken::JsonKey(String::from("num")),
-            JsonToken::JsonNum(123.0),
-            JsonToken::JsonKey(String::from("bool")),
-            JsonToken::JsonBool(true),
-            JsonToken::JsonObjEnd,
+        let json_token_vec: Vec<Token> = vec![
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("hello")),
+            Token::JsonString(String::from("world")),
+            Token::JsonKey(String::from("num")),
+            Token::JsonNum(123.0),
+            Token::JsonKey(String::from("bool")),
+            Token::JsonBool(true),
+            Token::JsonObjEnd,
         ];
 
-        let mut json_map: HashMap<String, JsonToken> = HashMap::new();
+        let mut json_map: JsonMap = JsonMap::new();
         json_map.insert(
             String::from("hello"),
-            JsonToken::JsonString(String::from("world")),
+            JsonValue::JsonString(String::from("world")),
         );
-        json_map.insert(String::from("num"), JsonToken::JsonNum(123.0));
-        json_map.insert(String::from("bool"), JsonToken::JsonBool(true));
+        json_map.insert(String::from("num"), JsonValue::JsonNum(123.0));
+        json_map.insert(String::from("bool"), JsonValue::JsonBool(true));
 
         let created_map = match json_tokens_to_json_object(&json_token_vec) {
-            JsonToken::JsonObj(map) => map,
-            _ => HashMap::new(),
+            Ok(JsonValue::JsonObj(map)) => map,
+            _ => JsonMap::new(),
         };
 
         assert_eq!(json_map, created_map);
@@ -730,37 +1077,37 @@ mod tests {
 
     #[test]
     fn converts_tokens_w_array_to_hashmap() {
-        let json_token_vec: Vec<JsonToken> = vec![
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("hello")),
-            JsonToken::JsonString(String::from("world")),
-            JsonToken::JsonKey(String::from("num")),
-            JsonToken::JsonNum(123.0),
-            JsonToken::JsonKey(String::from("bool")),
-            JsonToken::JsonBool(true),
-            JsonToken::JsonKey(String::from("array")),
-            JsonToken::JsonArrBeg,
-            JsonToken::JsonNum(123.0),
-            JsonToken::JsonBool(false),
-            JsonToken::JsonArrEnd,
-            JsonToken::JsonObjEnd,
+        let json_token_vec: Vec<Token> = vec![
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("hello")),
+            Token::JsonString(String::from("world")),
+            Token::JsonKey(String::from("num")),
+            Token::JsonNum(123.0),
+            Token::JsonKey(String::from("bool")),
+            Token::JsonBool(true),
+            Token::JsonKey(String::from("array")),
+            Token::JsonArrBeg,
+            Token::JsonNum(123.0),
+            Token::JsonBool(false),
+            Token::JsonArrEnd,
+            Token::JsonObjEnd,
         ];
 
-        let mut json_map: HashMap<String, JsonToken> = HashMap::new();
+        let mut json_map: JsonMap = JsonMap::new();
         json_map.insert(
             String::from("hello"),
-            JsonToken::JsonString(String::from("world")),
+            JsonValue::JsonString(String::from("world")),
         );
-        json_map.insert(String::from("num"), JsonToken::JsonNum(123.0));
-        json_map.insert(String::from("bool"), JsonToken::JsonBool(true));
+        json_map.insert(String::from("num"), JsonValue::JsonNum(123.0));
+        json_map.insert(String::from("bool"), JsonValue::JsonBool(true));
         json_map.insert(
             String::from("array"),
-            JsonToken::JsonArr(vec![JsonToken::JsonNum(123.0), JsonToken::JsonBool(false)]),
+            JsonValue::JsonArr(vec![JsonValue::JsonNum(123.0), JsonValue::JsonBool(false)]),
         );
 
         let created_map = match json_tokens_to_json_object(&json_token_vec) {
-            JsonToken::JsonObj(map) => map,
-            _ => HashMap::new(),
+            Ok(JsonValue::JsonObj(map)) => map,
+            _ => JsonMap::new(),
         };
 
         assert_eq!(json_map, created_map);
@@ -768,45 +1115,45 @@ mod tests {
 
     #[test]
     fn converts_tokens_w_nested_array_to_hashmap() {
-        let json_token_vec: Vec<JsonToken> = vec![
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("hello")),
-            JsonToken::JsonString(String::from("world")),
-            JsonToken::JsonKey(String::from("num")),
-            JsonToken::JsonNum(123.0),
-            JsonToken::JsonKey(String::from("bool")),
-            JsonToken::JsonBool(true),
-            JsonToken::JsonKey(String::from("array")),
-            JsonToken::JsonArrBeg,
-            JsonToken::JsonNum(123.0),
-            JsonToken::JsonBool(false),
-            JsonToken::JsonArrBeg,
-            JsonToken::JsonNum(321.0),
-            JsonToken::JsonBool(false),
-            JsonToken::JsonArrEnd,
-            JsonToken::JsonArrEnd,
-            JsonToken::JsonObjEnd,
+        let json_token_vec: Vec<Token> = vec![
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("hello")),
+            Token::JsonString(String::from("world")),
+            Token::JsonKey(String::from("num")),
+            Token::JsonNum(123.0),
+            Token::JsonKey(String::from("bool")),
+            Token::JsonBool(true),
+            Token::JsonKey(String::from("array")),
+            Token::JsonArrBeg,
+            Token::JsonNum(123.0),
+            Token::JsonBool(false),
+            Token::JsonArrBeg,
+            Token::JsonNum(321.0),
+            Token::JsonBool(false),
+            Token::JsonArrEnd,
+            Token::JsonArrEnd,
+            Token::JsonObjEnd,
         ];
 
-        let mut json_map: HashMap<String, JsonToken> = HashMap::new();
+        let mut json_map: JsonMap = JsonMap::new();
         json_map.insert(
             String::from("hello"),
-            JsonToken::JsonString(String::from("world")),
+            JsonValue::JsonString(String::from("world")),
         );
-        json_map.insert(String::from("num"), JsonToken::JsonNum(123.0));
-        json_map.insert(String::from("bool"), JsonToken::JsonBool(true));
+        json_map.insert(String::from("num"), JsonValue::JsonNum(123.0));
+        json_map.insert(String::from("bool"), JsonValue::JsonBool(true));
         json_map.insert(
             String::from("array"),
-            JsonToken::JsonArr(vec![
-                JsonToken::JsonNum(123.0),
-                JsonToken::JsonBool(false),
-                JsonToken::JsonArr(vec![JsonToken::JsonNum(321.0), JsonToken::JsonBool(false)]),
+            JsonValue::JsonArr(vec![
+                JsonValue::JsonNum(123.0),
+                JsonValue::JsonBool(false),
+                JsonValue::JsonArr(vec![JsonValue::JsonNum(321.0), JsonValue::JsonBool(false)]),
             ]),
         );
 
         let created_map = match json_tokens_to_json_object(&json_token_vec) {
-            JsonToken::JsonObj(map) => map,
-            _ => HashMap::new(),
+            Ok(JsonValue::JsonObj(map)) => map,
+            _ => JsonMap::new(),
         };
 
         assert_eq!(json_map, created_map);
@@ -814,42 +1161,810 @@ mod tests {
 
     #[test]
     fn converts_tokens_w_nested_object_to_hashmap() {
-        let json_token_vec: Vec<JsonToken> = vec![
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("hello")),
-            JsonToken::JsonString(String::from("world")),
-            JsonToken::JsonKey(String::from("num")),
-            JsonToken::JsonNum(123.0),
-            JsonToken::JsonKey(String::from("bool")),
-            JsonToken::JsonBool(true),
-            JsonToken::JsonKey(String::from("obj")),
-            JsonToken::JsonObjBeg,
-            JsonToken::JsonKey(String::from("key1")),
-            JsonToken::JsonString(String::from("nested obj test")),
-            JsonToken::JsonObjEnd,
-            JsonToken::JsonObjEnd,
+        let json_token_vec: Vec<Token> = vec![
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("hello")),
+            Token::JsonString(String::from("world")),
+            Token::JsonKey(String::from("num")),
+            Token::JsonNum(123.0),
+            Token::JsonKey(String::from("bool")),
+            Token::JsonBool(true),
+            Token::JsonKey(String::from("obj")),
+            Token::JsonObjBeg,
+            Token::JsonKey(String::from("key1")),
+            Token::JsonString(String::from("nested obj test")),
+            Token::JsonObjEnd,
+            Token::JsonObjEnd,
         ];
 
-        let mut nested_map: HashMap<String, JsonToken> = HashMap::new();
+        let mut nested_map: JsonMap = JsonMap::new();
         nested_map.insert(
             String::from("key1"),
-            JsonToken::JsonString(String::from("nested obj test")),
+            JsonValue::JsonString(String::from("nested obj test")),
         );
 
-        let mut json_map: HashMap<String, JsonToken> = HashMap::new();
+        let mut json_map: JsonMap = JsonMap::new();
         json_map.insert(
             String::from("hello"),
-            JsonToken::JsonString(String::from("world")),
+            JsonValue::JsonString(String::from("world")),
         );
-        json_map.insert(String::from("num"), JsonToken::JsonNum(123.0));
-        json_map.insert(String::from("bool"), JsonToken::JsonBool(true));
-        json_map.insert(String::from("obj"), JsonToken::JsonObj(nested_map));
+        json_map.insert(String::from("num"), JsonValue::JsonNum(123.0));
+        json_map.insert(String::from("bool"), JsonValue::JsonBool(true));
+        json_map.insert(String::from("obj"), JsonValue::JsonObj(nested_map));
 
         let created_map = match json_tokens_to_json_object(&json_token_vec) {
-            JsonToken::JsonObj(map) => map,
-            _ => HashMap::new(),
+            Ok(JsonValue::JsonObj(map)) => map,
+            _ => JsonMap::new(),
         };
 
         assert_eq!(json_map, created_map);
     }
+
+    #[test]
+    fn reads_basic_json_null() {
+        let json_string: String = String::from(r#"{"nothing": null}"#);
+        assert_eq!(
+            vec![
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("nothing")),
+                Token::JsonColon,
+                Token::JsonNull,
+                Token::JsonObjEnd
+            ],
+            tokenize_json_string(&json_string).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_bare_word() {
+        let json_string: String = String::from(r#"{"nothing": nil}"#);
+        assert!(from_json_string(&json_string).is_err());
+    }
+
+    #[test]
+    fn keeps_null_values_in_parsed_map() {
+        let json_string: String = String::from(r#"{"a": null, "b": [null, true], "c": 1.5}"#);
+
+        let mut json_map: JsonMap = JsonMap::new();
+        json_map.insert(String::from("a"), JsonValue::JsonNull);
+        json_map.insert(
+            String::from("b"),
+            JsonValue::JsonArr(vec![JsonValue::JsonNull, JsonValue::JsonBool(true)]),
+        );
+        json_map.insert(String::from("c"), JsonValue::JsonNum(1.5));
+
+        let created_map = from_json_string(&json_string).unwrap();
+
+        assert_eq!(json_map, created_map);
+        assert!(created_map["a"].is_null());
+        assert!(created_map["a"].as_null().is_ok());
+        assert!(created_map["c"].as_null().is_err());
+        assert!(created_map["c"].as_bool().is_err());
+        assert!(created_map["b"].as_vec().unwrap()[1].as_bool().unwrap());
+    }
+
+    #[test]
+    fn reads_negative_json_number() {
+        let json_string: String = String::from(r#"{"number": -5}"#);
+        assert_eq!(
+            vec![
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("number")),
+                Token::JsonColon,
+                Token::JsonNum(-5.0),
+                Token::JsonObjEnd
+            ],
+            tokenize_json_string(&json_string).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_json_numbers_following_grammar() {
+        let cases = [
+            ("0", 0.0),
+            ("-0", -0.0),
+            ("7", 7.0),
+            ("-5", -5.0),
+            ("10", 10.0),
+            ("0.5", 0.5),
+            ("-12.25", -12.25),
+            ("1e10", 1e10),
+            ("1E10", 1e10),
+            ("1e+2", 100.0),
+            ("2.5E-3", 2.5e-3),
+            ("-0.0e0", -0.0),
+            ("0e5", 0.0),
+        ];
+
+        for (number, expected) in cases {
+            let json_string = format!("[{}]", number);
+            assert_eq!(
+                vec![
+                    Token::JsonArrBeg,
+                    Token::JsonNum(expected),
+                    Token::JsonArrEnd
+                ],
+                tokenize_json_string(&json_string).unwrap(),
+                "{}",
+                number
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_json_numbers() {
+        let cases = [
+            "1.2.3", "007", "01", "-01", "-", "--1", "1.", "1.e5", "1e", "1e+", "1E-", "1e5.5",
+            "1e5e5", "1-2", "-.5", "0x10", "1true",
+        ];
+
+        for number in cases {
+            let json_string = format!(r#"{{"number": {}}}"#, number);
+            let error = from_json_string(&json_string).unwrap_err();
+
+            assert_eq!(&JsonErrorKind::InvalidNumber, error.kind(), "{}", number);
+            assert_eq!(11, error.offset(), "{}", number);
+        }
+
+        // Only `-` and digits start a number
+        let cases = [
+            ("+1", JsonErrorKind::UnexpectedCharacter('+')),
+            (".5", JsonErrorKind::UnexpectedCharacter('.')),
+            ("example", JsonErrorKind::InvalidLiteral),
+            ("E5", JsonErrorKind::InvalidLiteral),
+        ];
+        for (value, kind) in cases {
+            let json_string = format!(r#"{{"number": {}}}"#, value);
+            let error = from_json_string(&json_string).unwrap_err();
+
+            assert_eq!((&kind, 11), (error.kind(), error.offset()), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_single_digit_number_before_closing_bracket() {
+        let json_string: String = String::from(r#"{"a": 1, "b": [2]}"#);
+
+        let mut json_map: JsonMap = JsonMap::new();
+        json_map.insert(String::from("a"), JsonValue::JsonNum(1.0));
+        json_map.insert(
+            String::from("b"),
+            JsonValue::JsonArr(vec![JsonValue::JsonNum(2.0)]),
+        );
+
+        assert_eq!(json_map, from_json_string(&json_string).unwrap());
+    }
+
+    #[test]
+    fn decodes_json_string_escapes() {
+        let json_string: String = String::from(
+            r#"{"escapes": "\" \\ \/ \b \f \n \r \t", "unicode": "caf\u00e9 \u00C9"}"#,
+        );
+        assert_eq!(
+            vec![
+                Token::JsonObjBeg,
+                Token::JsonKey(String::from("escapes")),
+                Token::JsonColon,
+                Token::JsonString(String::from("\" \\ / \u{08} \u{0C} \n \r \t")),
+                Token::JsonComma,
+                Token::JsonKey(String::from("unicode")),
+                Token::JsonColon,
+                Token::JsonString(String::from("café É")),
+                Token::JsonObjEnd
+            ],
+            tokenize_json_string(&json_string).unwrap()
+        );
+    }
+
+    #[test]
+    fn decodes_escapes_in_keys() {
+        let json_string: String = String::from(r#"{"a\"b\\": "c\\", "A": 1.5}"#);
+
+        let mut json_map: JsonMap = JsonMap::new();
+        json_map.insert(
+            String::from("a\"b\\"),
+            JsonValue::JsonString(String::from("c\\")),
+        );
+        json_map.insert(String::from("A"), JsonValue::JsonNum(1.5));
+
+        assert_eq!(json_map, from_json_string(&json_string).unwrap());
+    }
+
+    #[test]
+    fn combines_surrogate_pairs() {
+        let json_string: String = String::from(r#"["\ud83d\ude00 \uD834\uDD1E"]"#);
+        assert_eq!(
+            vec![
+                Token::JsonArrBeg,
+                Token::JsonString(String::from("😀 𝄞")),
+                Token::JsonArrEnd
+            ],
+            tokenize_json_string(&json_string).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_json_strings() {
+        let cases = [
+            (r#"{"a": "\ud83d"}"#, JsonErrorKind::LoneSurrogate, 7),
+            (r#"{"a": "\ud83dx"}"#, JsonErrorKind::LoneSurrogate, 7),
+            (r#"{"a": "\ud83d\u0041"}"#, JsonErrorKind::LoneSurrogate, 7),
+            (r#"{"a": "\ude00"}"#, JsonErrorKind::LoneSurrogate, 7),
+            (r#"{"a": "\x41"}"#, JsonErrorKind::InvalidEscape, 7),
+            (r#"{"a": "\u00g1"}"#, JsonErrorKind::InvalidEscape, 7),
+            (r#"{"a": "\u00"}"#, JsonErrorKind::InvalidEscape, 7),
+            (
+                "{\"a\": \"line\nbreak\"}",
+                JsonErrorKind::ControlCharacter,
+                11,
+            ),
+            (
+                "{\"a\": \"tab\there\"}",
+                JsonErrorKind::ControlCharacter,
+                10,
+            ),
+            ("{\"a\u{01}\": \"b\"}", JsonErrorKind::ControlCharacter, 3),
+            (
+                r#"{"a": "unterminated}"#,
+                JsonErrorKind::UnterminatedString,
+                6,
+            ),
+        ];
+
+        for (json_string, kind, offset) in cases {
+            let error = tokenize_json_string(json_string).unwrap_err();
+
+            assert_eq!(&kind, error.kind(), "{}", json_string);
+            assert_eq!(offset, error.offset(), "{}", json_string);
+            assert!(from_json_string(json_string).is_err(), "{}", json_string);
+        }
+    }
+
+    #[test]
+    fn parses_any_top_level_value() {
+        let cases = [
+            (
+                "[1, 2, 3]",
+                JsonValue::JsonArr(vec![
+                    JsonValue::JsonNum(1.0),
+                    JsonValue::JsonNum(2.0),
+                    JsonValue::JsonNum(3.0),
+                ]),
+            ),
+            (
+                r#"[[1], {"a": []}]"#,
+                JsonValue::JsonArr(vec![
+                    JsonValue::JsonArr(vec![JsonValue::JsonNum(1.0)]),
+                    JsonValue::JsonObj(JsonMap::from([(
+                        String::from("a"),
+                        JsonValue::JsonArr(vec![]),
+                    )])),
+                ]),
+            ),
+            ("[]", JsonValue::JsonArr(vec![])),
+            (r#""hi""#, JsonValue::JsonString(String::from("hi"))),
+            (" 42 ", JsonValue::JsonNum(42.0)),
+            ("-1.5e3", JsonValue::JsonNum(-1500.0)),
+            ("true", JsonValue::JsonBool(true)),
+            ("null", JsonValue::JsonNull),
+        ];
+
+        for (json_string, expected) in cases {
+            assert_eq!(
+                expected,
+                parse_json_string(json_string).unwrap(),
+                "{}",
+                json_string
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_missing_or_multiple_top_level_values() {
+        for json_string in ["", "   ", "1 2", r#""a" "b""#, "[1] [2]", r#""key": 1"#] {
+            assert!(parse_json_string(json_string).is_err(), "{}", json_string);
+        }
+    }
+
+    #[test]
+    fn from_json_string_requires_top_level_object() {
+        assert!(from_json_string("[1, 2, 3]").is_err());
+        assert!(from_json_string(r#""hi""#).is_err());
+        assert_eq!(JsonMap::new(), from_json_string("{}").unwrap());
+    }
+
+    #[test]
+    fn keeps_object_keys_in_source_order() {
+        let json_string = r#"{"key2": 1, "key3": {"z": null, "a": null}, "key1": 2}"#;
+        let map = from_json_string(json_string).unwrap();
+
+        assert_eq!(vec!["key2", "key3", "key1"], map.keys().collect::<Vec<_>>());
+        assert_eq!(
+            vec!["z", "a"],
+            map["key3"].as_map().unwrap().keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            r#"{"key2":1,"key3":{"z":null,"a":null},"key1":2}"#,
+            parse_json_string(json_string).unwrap().to_string()
+        );
+    }
+
+    fn parse_with_duplicate_keys(
+        json_string: &str,
+        duplicate_keys: DuplicateKeys,
+    ) -> Result<JsonValue, JsonError> {
+        let options = ParserOptions {
+            duplicate_keys,
+            ..ParserOptions::default()
+        };
+        parse_json_string_with(json_string, &options)
+    }
+
+    #[test]
+    fn applies_duplicate_key_policy() {
+        let json_string = r#"{"a": 1, "b": 2, "a": [3], "a": 4}"#;
+        let num = JsonValue::JsonNum;
+
+        assert_eq!(
+            Ok(JsonValue::from(JsonMap::from([
+                (String::from("a"), num(4.0)),
+                (String::from("b"), num(2.0)),
+            ]))),
+            parse_with_duplicate_keys(json_string, DuplicateKeys::LastWins)
+        );
+        assert_eq!(
+            parse_json_string(json_string),
+            parse_with_duplicate_keys(json_string, DuplicateKeys::LastWins)
+        );
+        assert_eq!(
+            Ok(JsonValue::from(JsonMap::from([
+                (String::from("a"), num(1.0)),
+                (String::from("b"), num(2.0)),
+            ]))),
+            parse_with_duplicate_keys(json_string, DuplicateKeys::FirstWins)
+        );
+        assert_eq!(
+            Ok(JsonValue::from(JsonMap::from([
+                (
+                    String::from("a"),
+                    JsonValue::JsonArr(vec![
+                        num(1.0),
+                        JsonValue::JsonArr(vec![num(3.0)]),
+                        num(4.0)
+                    ])
+                ),
+                (String::from("b"), num(2.0)),
+            ]))),
+            parse_with_duplicate_keys(json_string, DuplicateKeys::CollectAll)
+        );
+    }
+
+    #[test]
+    fn collects_only_repeated_keys() {
+        let json_string = r#"{"a": [1], "b": {"c": 1, "c": 2}}"#;
+
+        assert_eq!(
+            parse_json_string(r#"{"a": [1], "b": {"c": [1, 2]}}"#),
+            parse_with_duplicate_keys(json_string, DuplicateKeys::CollectAll)
+        );
+    }
+
+    #[test]
+    fn reports_both_positions_of_duplicate_keys() {
+        let json_string = "{\n  \"a\": 1,\n  \"b\": {\"a\": 2},\n  \"a\": 3\n}";
+        let error = parse_with_duplicate_keys(json_string, DuplicateKeys::Error).unwrap_err();
+
+        assert_eq!(
+            &JsonErrorKind::DuplicateKey {
+                key: String::from("a"),
+                first_offset: 4,
+                first_line: 2,
+                first_column: 3,
+            },
+            error.kind()
+        );
+        assert_eq!((31, 4, 3), (error.offset(), error.line(), error.column()));
+        assert_eq!(
+            "duplicate key \"a\" (first seen at line 2 column 3) at line 4 column 3",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_garbage() {
+        for (json_string, offset) in [
+            (r#"{"a":1} hello ]]"#, 8),
+            (r#"{"a":1}xyz"#, 7),
+            ("[1, 2]\n\n]", 8),
+            ("\"hi\" \u{0}", 5),
+        ] {
+            let error = parse_json_string(json_string).unwrap_err();
+            assert_eq!(
+                (&JsonErrorKind::TrailingData, offset),
+                (error.kind(), error.offset()),
+                "{}",
+                json_string
+            );
+            assert!(from_json_string(json_string).is_err(), "{}", json_string);
+        }
+
+        // The tokenizer has no grammar to end a document, but still rejects what is no token
+        assert!(tokenize_json_string(r#"{"a":1}xyz"#).is_err());
+        assert!(tokenize_json_string(r#"{"a":1} hello ]]"#).is_err());
+    }
+
+    #[test]
+    fn parses_value_prefixes() {
+        assert_eq!(
+            Ok((JsonValue::JsonArr(vec![JsonValue::JsonNum(1.0)]), "x")),
+            parse_json_prefix(" [1]x")
+        );
+        assert_eq!(
+            Ok((JsonValue::JsonNum(12.0), " , {}")),
+            parse_json_prefix("12 , {}")
+        );
+        assert_eq!(
+            Ok((JsonValue::from("a"), "\"b\"")),
+            parse_json_prefix(r#""a""b""#)
+        );
+        assert_eq!(Ok((JsonValue::JsonNull, "")), parse_json_prefix("null"));
+
+        let mut values = Vec::new();
+        let mut rest = r#"{"a": 1} [true] "end" tail"#;
+        while let Ok((value, after)) = parse_json_prefix(rest) {
+            values.push(value.to_string());
+            rest = after;
+        }
+        assert_eq!(vec![r#"{"a":1}"#, "[true]", r#""end""#], values);
+        assert_eq!(" tail", rest);
+
+        for json_string in ["", "  ", "]", "123abc", "truex", "[1,"] {
+            assert!(parse_json_prefix(json_string).is_err(), "{}", json_string);
+        }
+    }
+
+    fn nested_arrays(depth: usize) -> String {
+        format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        assert!(parse_json_string(&nested_arrays(128)).is_ok());
+
+        let error = parse_json_string(&nested_arrays(129)).unwrap_err();
+        assert_eq!(
+            (&JsonErrorKind::TooDeep, 128),
+            (error.kind(), error.offset())
+        );
+
+        // Far deeper than the stack could take, the limit stops it long before
+        let error = parse_json_string(&"[{\"a\":".repeat(1_000_000)).unwrap_err();
+        assert_eq!(&JsonErrorKind::TooDeep, error.kind());
+
+        let options = ParserOptions {
+            max_depth: 2,
+            ..ParserOptions::default()
+        };
+        assert!(parse_json_string_with(r#"{"a": [1], "b": []}"#, &options).is_ok());
+        assert_eq!(
+            &JsonErrorKind::TooDeep,
+            parse_json_string_with(r#"{"a": [{}]}"#, &options)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn limits_string_length() {
+        let options = ParserOptions {
+            max_string_bytes: Some(4),
+            ..ParserOptions::default()
+        };
+
+        assert!(parse_json_string_with(r#"["abcd", "\u00e9\u00e9"]"#, &options).is_ok());
+        for (json_string, offset) in [
+            (r#"["abcde"]"#, 1),
+            (r#"{"a": 1, "keyed": 2}"#, 9),
+            (r#"["ab\n\ncd"]"#, 1),
+            (r#"["ab\u00e9\u00e9"]"#, 1),
+        ] {
+            let error = parse_json_string_with(json_string, &options).unwrap_err();
+            assert_eq!(
+                (&JsonErrorKind::StringTooLong, offset),
+                (error.kind(), error.offset()),
+                "{}",
+                json_string
+            );
+        }
+    }
+
+    #[test]
+    fn limits_container_members() {
+        let options = ParserOptions {
+            max_members: Some(2),
+            duplicate_keys: DuplicateKeys::LastWins,
+            ..ParserOptions::default()
+        };
+
+        assert!(parse_json_string_with(r#"[[1, 2], {"a": 1, "b": 2}]"#, &options).is_ok());
+        for (json_string, offset) in [
+            ("[1, 2, 3]", 0),
+            (r#"[{"a": 1, "b": 2, "c": 3}]"#, 1),
+            // Repeated keys still count, although the map only keeps one of them
+            (r#"{"a": 1, "a": 2, "a": 3}"#, 0),
+        ] {
+            let error = parse_json_string_with(json_string, &options).unwrap_err();
+            assert_eq!(
+                (&JsonErrorKind::TooManyMembers, offset),
+                (error.kind(), error.offset()),
+                "{}",
+                json_string
+            );
+        }
+    }
+
+    #[test]
+    fn limits_input_size() {
+        let options = ParserOptions {
+            max_input_bytes: Some(8),
+            ..ParserOptions::default()
+        };
+
+        assert!(parse_json_string_with("[1, 2]  ", &options).is_ok());
+        assert_eq!(
+            &JsonErrorKind::InputTooLarge,
+            parse_json_string_with("[1, 2]   ", &options)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn reports_error_position() {
+        let json_string = "{\n  \"a\": 1,\n  \"b\": tru\n}";
+        let error = from_json_string(json_string).unwrap_err();
+
+        assert_eq!(&JsonErrorKind::InvalidLiteral, error.kind());
+        assert_eq!(19, error.offset());
+        assert_eq!(3, error.line());
+        assert_eq!(8, error.column());
+        assert_eq!("invalid literal at line 3 column 8", error.to_string());
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let error = parse_json_string(r#"["éé", @]"#).unwrap_err();
+
+        assert_eq!(&JsonErrorKind::UnexpectedCharacter('@'), error.kind());
+        assert_eq!(9, error.offset());
+        assert_eq!(8, error.column());
+    }
+
+    #[test]
+    fn reports_structural_error_kinds() {
+        let cases = [
+            ("", JsonErrorKind::UnexpectedEnd, 0),
+            ("[1, 2", JsonErrorKind::UnclosedContainer, 0),
+            (
+                r#"{"a": [1, 2}"#,
+                JsonErrorKind::UnexpectedCharacter('}'),
+                11,
+            ),
+            ("[{]}", JsonErrorKind::UnexpectedCharacter(']'), 2),
+            ("]{[}", JsonErrorKind::UnexpectedCharacter(']'), 0),
+            ("{[]}", JsonErrorKind::ExpectedKey, 1),
+            (r#"{"a": 1, 2}"#, JsonErrorKind::ExpectedKey, 9),
+            (r#"{"a" 1}"#, JsonErrorKind::MissingColon, 5),
+            (r#"{"a": 1 "b": 2}"#, JsonErrorKind::MissingComma, 8),
+            ("[1 2]", JsonErrorKind::MissingComma, 3),
+            ("[1, 2,]", JsonErrorKind::TrailingComma, 6),
+            (r#"{"a": 1,}"#, JsonErrorKind::TrailingComma, 8),
+            ("[1,,2]", JsonErrorKind::ExpectedValue, 3),
+            ("[,]", JsonErrorKind::ExpectedValue, 1),
+            (r#"{"a":}"#, JsonErrorKind::ExpectedValue, 5),
+            (r#"{"a"::1}"#, JsonErrorKind::ExpectedValue, 5),
+            (r#"["a": 1]"#, JsonErrorKind::UnexpectedCharacter(':'), 4),
+            (r#"{"a": 1"#, JsonErrorKind::UnclosedContainer, 0),
+            (r#"{"a":"#, JsonErrorKind::UnclosedContainer, 0),
+            ("[1] 2", JsonErrorKind::TrailingData, 4),
+            ("[1]]", JsonErrorKind::TrailingData, 3),
+            ("1,", JsonErrorKind::TrailingData, 1),
+            (",1", JsonErrorKind::ExpectedValue, 0),
+        ];
+
+        for (json_string, kind, offset) in cases {
+            let error = parse_json_string(json_string).unwrap_err();
+
+            assert_eq!(&kind, error.kind(), "{}", json_string);
+            assert_eq!(offset, error.offset(), "{}", json_string);
+        }
+
+        assert_eq!(
+            &JsonErrorKind::NotAnObject,
+            from_json_string("  [1]").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn accepts_valid_json_grammar() {
+        let cases = [
+            "{}",
+            "[]",
+            "[[]]",
+            r#"{"a": {}}"#,
+            r#"[{}, [], {"a": [1, {"b": null}]}]"#,
+            r#"{"a": 1, "b": [true, false], "c": {"d": "e"}}"#,
+            " [ 1 , 2 ] ",
+        ];
+
+        for json_string in cases {
+            assert!(parse_json_string(json_string).is_ok(), "{}", json_string);
+        }
+    }
+
+    #[test]
+    fn recursive_descent_matches_token_folding() {
+        let cases = [
+            r#"{ "key1": "Hello, World!", "key2": [123, true, ["I am nested!"]], "key3": { "nested-obj": "Hello up there!"} }"#,
+            r#"[{"a": [[], {}]}, -1.5e2, "x\u00e9", null, false]"#,
+            "[1, 2",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            "[1 2]",
+            "]{[}",
+            "[1] 2",
+        ];
+
+        for json_string in cases {
+            assert_eq!(
+                parse_json_string_by_tokens(json_string),
+                parse_json_string(json_string),
+                "{}",
+                json_string
+            );
+        }
+    }
+
+    #[test]
+    fn converts_scalar_tokens_to_values() {
+        assert_eq!(
+            Ok(JsonValue::JsonString(String::from("a"))),
+            JsonValue::try_from(Token::JsonString(String::from("a")))
+        );
+        assert_eq!(Some(JsonValue::JsonNull), Token::JsonNull.to_value());
+        assert_eq!(
+            Some(JsonValue::JsonNum(1.5)),
+            Token::JsonNum(1.5).to_value()
+        );
+        assert_eq!(
+            Err(Token::JsonKey(String::from("a"))),
+            JsonValue::try_from(Token::JsonKey(String::from("a")))
+        );
+        assert_eq!(None, Token::JsonArrBeg.to_value());
+        assert_eq!(JsonValue::JsonBool(true), JsonValue::from(true));
+        assert_eq!(
+            JsonValue::JsonArr(vec![JsonValue::JsonString(String::from("a"))]),
+            JsonValue::from(vec![JsonValue::from("a")])
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn json_token_alias_names_parsed_values() {
+        let parsed: JsonToken = parse_json_string("[null]").unwrap();
+        assert_eq!(JsonToken::JsonArr(vec![JsonToken::JsonNull]), parsed);
+    }
+
+    #[test]
+    fn rejects_invalid_token_lists() {
+        let key = || Token::JsonKey(String::from("a"));
+
+        for (json_token_vec, kind) in [
+            (vec![], JsonErrorKind::UnexpectedEnd),
+            (vec![Token::JsonObjBeg], JsonErrorKind::UnclosedContainer),
+            (
+                vec![Token::JsonObjBeg, key()],
+                JsonErrorKind::UnclosedContainer,
+            ),
+            (
+                vec![Token::JsonObjBeg, key(), Token::JsonObjEnd],
+                JsonErrorKind::ExpectedValue,
+            ),
+            (
+                vec![Token::JsonObjBeg, key(), key(), Token::JsonNull],
+                JsonErrorKind::ExpectedValue,
+            ),
+            (
+                vec![Token::JsonObjBeg, Token::JsonNull, Token::JsonObjEnd],
+                JsonErrorKind::ExpectedKey,
+            ),
+            (
+                vec![Token::JsonArrBeg, Token::JsonObjEnd],
+                JsonErrorKind::UnexpectedCharacter('}'),
+            ),
+            (
+                vec![Token::JsonArrEnd],
+                JsonErrorKind::UnexpectedCharacter(']'),
+            ),
+            (
+                vec![Token::JsonObjBeg, Token::JsonObjEnd, Token::JsonObjBeg],
+                JsonErrorKind::TrailingData,
+            ),
+            (
+                vec![Token::JsonArrBeg, Token::JsonArrEnd],
+                JsonErrorKind::NotAnObject,
+            ),
+        ] {
+            assert_eq!(
+                Err(kind),
+                json_tokens_to_json_object(&json_token_vec),
+                "{:?}",
+                json_token_vec
+            );
+        }
+    }
+
+    #[test]
+    fn limits_the_nesting_of_token_lists() {
+        let nested = |depth: usize| {
+            let mut json_token_vec = vec![Token::JsonObjBeg, Token::JsonKey(String::from("a"))];
+            json_token_vec.extend(std::iter::repeat_n(Token::JsonArrBeg, depth));
+            json_token_vec.extend(std::iter::repeat_n(Token::JsonArrEnd, depth));
+            json_token_vec.push(Token::JsonObjEnd);
+            json_token_vec
+        };
+
+        assert!(json_tokens_to_json_object(&nested(127)).is_ok());
+        assert_eq!(
+            Err(JsonErrorKind::TooDeep),
+            json_tokens_to_json_object(&nested(100_000))
+        );
+
+        let json_string = format!("{}{}", "[".repeat(200_000), "]".repeat(200_000));
+        let error = parse_json_string_by_tokens(&json_string).unwrap_err();
+        assert_eq!(
+            (&JsonErrorKind::TooDeep, 128),
+            (error.kind(), error.offset())
+        );
+        assert_eq!(Err(error), parse_json_string(&json_string));
+    }
+
+    // Exercises every public way of reading input, none of them may panic
+    fn parse_every_way(json_string: &str) {
+        let _ = tokenize_json_string(json_string);
+        let _ = parse_json_string(json_string);
+        let _ = parse_json_string_by_tokens(json_string);
+        let _ = parse_json_prefix(json_string);
+        let _ = from_json_string(json_string);
+        let _ = parse_json_slice(json_string.as_bytes());
+        let _ = parse_json_reader(json_string.as_bytes());
+        for duplicate_keys in [DuplicateKeys::Error, DuplicateKeys::CollectAll] {
+            let options = ParserOptions {
+                duplicate_keys,
+                max_depth: 4,
+                max_string_bytes: Some(4),
+                max_members: Some(4),
+                max_input_bytes: Some(64),
+            };
+            let _ = parse_json_string_with(json_string, &options);
+        }
+        if let Ok(json_token_vec) = tokenize_json_string(json_string) {
+            let _ = json_tokens_to_json_object(&json_token_vec);
+            let _ = json_tokens_to_json_object(&json_token_vec[..json_token_vec.len() / 2]);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics_on_arbitrary_input(json_string in any::<String>()) {
+            parse_every_way(&json_string);
+        }
+
+        #[test]
+        fn never_panics_on_json_like_input(
+            json_string in r#"[\[\]{}:,"\\ u0-9a-fE.+\-éntrl\n\x00]{0,40}"#
+        ) {
+            parse_every_way(&json_string);
+        }
+    }
 }
@@ -0,0 +1,243 @@
+use std::{collections::HashMap, fmt, ops::Index};
+
+use crate::JsonValue;
+
+/// The members of a JSON object, kept in insertion order.
+///
+/// Iteration and serialization follow the order the keys were first inserted in, which for
+/// parsed documents is their order in the source text. Lookups go through a hash index and
+/// stay O(1). Two maps are equal when they hold the same members, whatever their order.
+#[derive(Clone, Default)]
+pub struct JsonMap {
+    entries: Vec<(String, JsonValue)>,
+    index: HashMap<String, usize>,
+}
+
+impl JsonMap {
+    pub fn new() -> Self {
+        JsonMap::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        JsonMap {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        match self.index.get(key) {
+            Some(&i) => Some(&mut self.entries[i].1),
+            None => None,
+        }
+    }
+
+    /// Inserts a member and returns the value it replaced. A replaced key keeps its place,
+    /// a new key goes at the end.
+    pub fn insert(&mut self, key: String, value: JsonValue) -> Option<JsonValue> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes a member and returns its value. The members after it move up one place, so
+    /// this is O(n) in the size of the map.
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        let removed = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(removed);
+
+        for i in self.index.values_mut() {
+            if *i > removed {
+                *i -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&String, &JsonValue)> + ExactSizeIterator {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = (&String, &mut JsonValue)> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &String> + ExactSizeIterator {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &JsonValue> + ExactSizeIterator {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut JsonValue> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+}
+
+impl PartialEq for JsonMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|other| value == other))
+    }
+}
+
+impl fmt::Debug for JsonMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Index<&str> for JsonMap {
+    type Output = JsonValue;
+
+    /// Panics if the key is missing, use `get` to check first.
+    fn index(&self, key: &str) -> &JsonValue {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("no member named {:?} in the JSON object", key),
+        }
+    }
+}
+
+impl FromIterator<(String, JsonValue)> for JsonMap {
+    fn from_iter<I: IntoIterator<Item = (String, JsonValue)>>(iter: I) -> Self {
+        let mut map = JsonMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl Extend<(String, JsonValue)> for JsonMap {
+    fn extend<I: IntoIterator<Item = (String, JsonValue)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<const N: usize> From<[(String, JsonValue); N]> for JsonMap {
+    fn from(members: [(String, JsonValue); N]) -> Self {
+        members.into_iter().collect()
+    }
+}
+
+/// The members are added in the map's iteration order, which is arbitrary.
+impl From<HashMap<String, JsonValue>> for JsonMap {
+    fn from(map: HashMap<String, JsonValue>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<JsonMap> for HashMap<String, JsonValue> {
+    fn from(map: JsonMap) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl IntoIterator for JsonMap {
+    type Item = (String, JsonValue);
+    type IntoIter = std::vec::IntoIter<(String, JsonValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a JsonMap {
+    type Item = (&'a String, &'a JsonValue);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, JsonValue)>,
+        fn(&'a (String, JsonValue)) -> (&'a String, &'a JsonValue),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(num: f64) -> JsonValue {
+        JsonValue::JsonNum(num)
+    }
+
+    #[test]
+    fn keeps_insertion_order() {
+        let mut map = JsonMap::new();
+        map.insert(String::from("b"), num(1.0));
+        map.insert(String::from("a"), num(2.0));
+        map.insert(String::from("c"), num(3.0));
+        map.insert(String::from("a"), num(4.0));
+
+        assert_eq!(vec!["b", "a", "c"], map.keys().collect::<Vec<_>>());
+        assert_eq!(Some(&num(4.0)), map.get("a"));
+        assert_eq!(3, map.len());
+    }
+
+    #[test]
+    fn removes_members_and_keeps_lookups_working() {
+        let mut map = JsonMap::from([
+            (String::from("a"), num(1.0)),
+            (String::from("b"), num(2.0)),
+            (String::from("c"), num(3.0)),
+        ]);
+
+        assert_eq!(Some(num(1.0)), map.remove("a"));
+        assert_eq!(None, map.remove("a"));
+        assert_eq!(Some(&num(3.0)), map.get("c"));
+
+        map.insert(String::from("a"), num(5.0));
+        assert_eq!(vec!["b", "c", "a"], map.keys().collect::<Vec<_>>());
+        assert_eq!(Some(&num(2.0)), map.get("b"));
+        assert_eq!(num(5.0), map["a"]);
+    }
+
+    #[test]
+    fn compares_members_regardless_of_order() {
+        let ab = JsonMap::from([(String::from("a"), num(1.0)), (String::from("b"), num(2.0))]);
+        let ba = JsonMap::from([(String::from("b"), num(2.0)), (String::from("a"), num(1.0))]);
+        let other = JsonMap::from([(String::from("a"), num(1.0)), (String::from("b"), num(3.0))]);
+
+        assert_eq!(ab, ba);
+        assert_ne!(ab, other);
+        assert_ne!(ab, JsonMap::new());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{JsonMap, JsonValue};
+
+/// JSON Merge Patch (RFC 7396). A merge patch looks like the document it changes: object
+/// members in the patch are merged into the document, `null` removes a member, and any other
+/// value, arrays included, replaces what was there.
+impl JsonValue {
+    /// Applies a merge patch.
+    ///
+    /// ```
+    /// use json_reader::parse_json_string;
+    ///
+    /// let mut user = parse_json_string(r#"{"name": "Ada", "phone": "123", "tags": ["a"]}"#)?;
+    /// user.merge_patch(&parse_json_string(r#"{"phone": null, "tags": ["b"]}"#)?);
+    ///
+    /// assert_eq!(parse_json_string(r#"{"name": "Ada", "tags": ["b"]}"#)?, user);
+    /// # Ok::<(), json_reader::JsonError>(())
+    /// ```
+    pub fn merge_patch(&mut self, patch: &JsonValue) {
+        let JsonValue::JsonObj(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+
+        let mut map = match self {
+            JsonValue::JsonObj(map) => std::mem::take(map),
+            _ => JsonMap::new(),
+        };
+        for (key, value) in patch.iter() {
+            if value.is_null() {
+                map.remove(key);
+                continue;
+            }
+            match map.get_mut(key) {
+                Some(member) => member.merge_patch(value),
+                None => {
+                    let mut member = JsonValue::JsonNull;
+                    member.merge_patch(value);
+                    map.insert(key.clone(), member);
+                }
+            }
+        }
+        *self = JsonValue::JsonObj(map);
+    }
+
+    /// A merge patch that turns `from` into `to`.
+    ///
+    /// Objects are diffed member by member, anything else that differs is replaced whole.
+    /// A merge patch can't set a member to `null`, so members that are `null` in `to` are
+    /// missing from the patched value.
+    pub fn merge_patch_diff(from: &JsonValue, to: &JsonValue) -> JsonValue {
+        let (JsonValue::JsonObj(from), JsonValue::JsonObj(to)) = (from, to) else {
+            return to.clone();
+        };
+
+        let mut patch = JsonMap::new();
+        for key in from.keys() {
+            if !to.contains_key(key) {
+                patch.insert(key.clone(), JsonValue::JsonNull);
+            }
+        }
+        for (key, value) in to.iter() {
+            match from.get(key) {
+                Some(old) if old == value => (),
+                Some(old) => {
+                    patch.insert(key.clone(), JsonValue::merge_patch_diff(old, value));
+                }
+                None => {
+                    patch.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        JsonValue::JsonObj(patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::{parse_json_string, JsonValue};
+
+    fn json(json_string: &str) -> JsonValue {
+        parse_json_string(json_string).unwrap()
+    }
+
+    // The examples of RFC 7396, Appendix A
+    const RFC_EXAMPLES: &[(&str, &str, &str)] = &[
+        (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+        (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+        (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+        (
+            r#"{"a":{"b":"c"}}"#,
+            r#"{"a":{"b":"d","c":null}}"#,
+            r#"{"a":{"b":"d"}}"#,
+        ),
+        (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+        (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+        (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+        (r#"{"a":"foo"}"#, "null", "null"),
+        (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+        (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+        (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+        (
+            r#"{}"#,
+            r#"{"a":{"bb":{"ccc":null}}}"#,
+            r#"{"a":{"bb":{}}}"#,
+        ),
+    ];
+
+    #[test]
+    fn follows_the_rfc_examples() {
+        for (target, patch, expected) in RFC_EXAMPLES {
+            let mut value = json(target);
+            value.merge_patch(&json(patch));
+
+            assert_eq!(json(expected), value, "{} patched with {}", target, patch);
+        }
+    }
+
+    #[test]
+    fn diffs_objects_member_by_member() {
+        let from = json(
+            r#"{"title": "Goodbye!", "author": {"given": "John", "family": "Doe"}, "tags": ["a", "b"], "n": 1}"#,
+        );
+        let to = json(
+            r#"{"title": "Hello!", "author": {"given": "John"}, "tags": ["a"], "n": 1, "phone": "+01"}"#,
+        );
+
+        assert_eq!(
+            r#"{"title":"Hello!","author":{"family":null},"tags":["a"],"phone":"+01"}"#,
+            JsonValue::merge_patch_diff(&from, &to).to_string()
+        );
+        assert_eq!(json("{}"), JsonValue::merge_patch_diff(&from, &from));
+        assert_eq!(
+            json("[1]"),
+            JsonValue::merge_patch_diff(&json("[1]"), &json("[1]"))
+        );
+    }
+
+    fn arb_value_without_null() -> impl Strategy<Value = JsonValue> {
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(JsonValue::JsonBool),
+            (0..3u8).prop_map(|n| JsonValue::JsonNum(f64::from(n))),
+            "[ab]".prop_map(JsonValue::JsonString),
+        ];
+
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(JsonValue::JsonArr),
+                prop::collection::vec(("[abc]", inner), 0..4)
+                    .prop_map(|members| JsonValue::JsonObj(members.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn diff_turns_one_value_into_the_other(
+            from in arb_value_without_null(),
+            to in arb_value_without_null(),
+        ) {
+            let mut patched = from.clone();
+            patched.merge_patch(&JsonValue::merge_patch_diff(&from, &to));
+
+            prop_assert_eq!(to, patched);
+        }
+    }
+}
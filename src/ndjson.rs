@@ -0,0 +1,299 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{
+    parser::parse_bytes, JsonError, JsonErrorKind, JsonValue, ParserOptions, TextPosition,
+};
+
+/// Reader of newline-delimited JSON (NDJSON, also called JSON Lines), one value per line.
+///
+/// Iterates over the result of parsing each line. Errors are positioned in the whole input, so
+/// `JsonError::line` is the number of the line that failed. A bad line doesn't end the
+/// iteration unless `stop_on_error` is set, a failed read always does. Lines holding nothing
+/// but whitespace are skipped, and a line can end with `\n` or `\r\n`.
+///
+/// `ParserOptions` apply to each line on its own, `max_input_bytes` bounds the length of a line.
+/// A longer line isn't buffered, it is skipped and reported as `InputTooLarge`.
+///
+/// ```
+/// use json_reader::{JsonValue, NdjsonReader};
+///
+/// let input = "{\"id\": 1}\n{\"id\": \n[2]\n";
+/// let lines: Vec<_> = NdjsonReader::new(input.as_bytes()).collect();
+///
+/// assert!(lines[0].is_ok());
+/// assert_eq!(2, lines[1].as_ref().unwrap_err().line());
+/// assert_eq!(Ok(JsonValue::JsonArr(vec![JsonValue::JsonNum(2.0)])), lines[2]);
+/// ```
+pub struct NdjsonReader<R> {
+    reader: R,
+    options: ParserOptions,
+    stop_on_error: bool,
+    line: Vec<u8>,
+    // Where the next line starts
+    position: TextPosition,
+    done: bool,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> NdjsonReader<R> {
+        NdjsonReader::with_options(reader, &ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: &ParserOptions) -> NdjsonReader<R> {
+        NdjsonReader {
+            reader,
+            options: options.clone(),
+            stop_on_error: false,
+            line: Vec::new(),
+            position: TextPosition::START,
+            done: false,
+        }
+    }
+
+    /// Ends the iteration after the first line that fails to parse.
+    pub fn stop_on_error(mut self, stop_on_error: bool) -> NdjsonReader<R> {
+        self.stop_on_error = stop_on_error;
+        self
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Reads the next line into `self.line`, giving its length and whether it is longer than
+    // `max_input_bytes`. Past that length, the rest of the line is skipped instead of read
+    fn read_line(&mut self) -> io::Result<(usize, bool)> {
+        self.line.clear();
+        let Some(max) = self.options.max_input_bytes else {
+            return Ok((self.reader.read_until(b'\n', &mut self.line)?, false));
+        };
+
+        let mut len = (&mut self.reader)
+            .take((max as u64).saturating_add(1))
+            .read_until(b'\n', &mut self.line)?;
+        if len <= max {
+            return Ok((len, false));
+        }
+        if self.line.last() != Some(&b'\n') {
+            len += skip_line(&mut self.reader)?;
+        }
+        Ok((len, true))
+    }
+}
+
+// Consumes everything up to and including the next `\n`, giving how many bytes that was
+fn skip_line<R: BufRead>(reader: &mut R) -> io::Result<usize> {
+    let mut skipped = 0;
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok(buf) => buf,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        let (len, found) = match buf.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (buf.len(), false),
+        };
+
+        reader.consume(len);
+        skipped += len;
+        if found || len == 0 {
+            return Ok(skipped);
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<JsonValue, JsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.read_line() {
+                Ok((0, _)) => self.done = true,
+                Ok((len, too_large)) => {
+                    let start = self.position;
+                    // Whatever the line holds, the next one starts at its first column
+                    self.position = TextPosition {
+                        offset: start.offset + len,
+                        line: start.line + 1,
+                        column: 1,
+                    };
+
+                    if too_large {
+                        self.done = self.stop_on_error;
+                        return Some(Err(JsonError::at(JsonErrorKind::InputTooLarge, start)));
+                    }
+                    if self
+                        .line
+                        .iter()
+                        .all(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+                    {
+                        continue;
+                    }
+
+                    let result = parse_bytes(&self.line, start, &self.options);
+                    if result.is_err() && self.stop_on_error {
+                        self.done = true;
+                    }
+                    return Some(result);
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(JsonError::at(
+                        JsonErrorKind::Io(error.kind()),
+                        self.position,
+                    )));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<R: BufRead> std::iter::FusedIterator for NdjsonReader<R> {}
+
+/// Writer of newline-delimited JSON, each value as compact JSON on a line of its own.
+///
+/// Compact JSON has no line breaks, the ones in strings are escaped, so every value written
+/// reads back as one line of an `NdjsonReader`.
+pub struct NdjsonWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> NdjsonWriter<W> {
+        NdjsonWriter { writer }
+    }
+
+    /// Writes a value and the `\n` that ends its line.
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        writeln!(self.writer, "{}", value)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json_string, JsonMap};
+
+    fn read_all(input: &[u8]) -> Vec<Result<JsonValue, JsonError>> {
+        NdjsonReader::new(input).collect()
+    }
+
+    #[test]
+    fn reads_a_value_per_line() {
+        let values = read_all(b"{\"a\": 1}\r\n\n  \n[true]\n\"x\"");
+
+        assert_eq!(
+            vec![
+                parse_json_string("{\"a\": 1}"),
+                parse_json_string("[true]"),
+                parse_json_string("\"x\""),
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn reports_bad_lines_with_their_line_number() {
+        let values = read_all(b"1\n[1,\n\n  {\"a\" 2}\n\"\xff\"\n3\n4 5\n");
+
+        let errors: Vec<_> = values
+            .iter()
+            .filter_map(|value| value.as_ref().err())
+            .map(|error| (error.kind().clone(), error.line(), error.column()))
+            .collect();
+        assert_eq!(
+            vec![
+                (JsonErrorKind::UnclosedContainer, 2, 1),
+                (JsonErrorKind::MissingColon, 4, 8),
+                (JsonErrorKind::InvalidUtf8, 5, 2),
+                (JsonErrorKind::TrailingData, 7, 3),
+            ],
+            errors
+        );
+        assert_eq!(6, values.len());
+        assert_eq!(Ok(JsonValue::JsonNum(3.0)), values[4]);
+
+        let error = values[2].as_ref().unwrap_err();
+        assert_eq!(14, error.offset());
+    }
+
+    #[test]
+    fn skips_lines_over_the_size_limit() {
+        let options = ParserOptions {
+            max_input_bytes: Some(8),
+            ..ParserOptions::default()
+        };
+        let mut input = b"1\n[1, 2, 3, 4]\n[1, 2]\n\"".to_vec();
+        input.resize(input.len() + (1 << 20), b'x');
+        let mut reader =
+            NdjsonReader::with_options(io::BufReader::with_capacity(64, &input[..]), &options);
+
+        assert_eq!(Some(Ok(JsonValue::JsonNum(1.0))), reader.next());
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            (&JsonErrorKind::InputTooLarge, 2, 2),
+            (error.kind(), error.line(), error.offset())
+        );
+        assert!(reader.next().unwrap().is_ok());
+
+        // A line without an end is skipped to the end of the input, not held in memory
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            (&JsonErrorKind::InputTooLarge, 4, 22),
+            (error.kind(), error.line(), error.offset())
+        );
+        assert!(reader.line.capacity() < 64);
+        assert_eq!(None, reader.next());
+    }
+
+    #[test]
+    fn stops_on_the_first_error_when_asked() {
+        let values: Vec<_> = NdjsonReader::new(&b"1\nx\n2\n"[..])
+            .stop_on_error(true)
+            .collect();
+
+        assert_eq!(2, values.len());
+        assert!(values[1].is_err());
+    }
+
+    #[test]
+    fn writes_values_that_read_back() {
+        let values = vec![
+            JsonValue::JsonObj(JsonMap::from([(
+                String::from("text"),
+                JsonValue::JsonString(String::from("two\nlines")),
+            )])),
+            JsonValue::JsonArr(vec![JsonValue::JsonNull, JsonValue::JsonNum(1.5)]),
+            JsonValue::JsonBool(false),
+        ];
+
+        let mut writer = NdjsonWriter::new(Vec::new());
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        let output = writer.into_inner();
+
+        assert_eq!(
+            "{\"text\":\"two\\nlines\"}\n[null,1.5]\nfalse\n",
+            String::from_utf8(output.clone()).unwrap()
+        );
+        assert_eq!(
+            values,
+            read_all(&output)
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,49 @@
+/// What the parser does when an object has the same key more than once.
+///
+/// RFC 8259 leaves this to the implementation, and parsers that pick differently can be made
+/// to disagree about what a document says. `Error` is the safe choice for input that another
+/// system also reads.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DuplicateKeys {
+    /// Reject the document with `JsonErrorKind::DuplicateKey`, which holds the position of the
+    /// first occurrence while the error itself points at the second.
+    Error,
+    /// Keep the first value and ignore later ones.
+    FirstWins,
+    /// Keep the last value, in the position the key was first seen at.
+    #[default]
+    LastWins,
+    /// Keep every value. A key seen more than once gets a `JsonArr` of all its values in
+    /// source order, a key seen once keeps its value as is.
+    CollectAll,
+}
+
+/// Settings for `parse_json_string_with`. The default matches `parse_json_string`.
+///
+/// The limits bound the time, memory and stack spent on untrusted input. Going over one is an
+/// error of its own kind, see `JsonErrorKind`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParserOptions {
+    pub duplicate_keys: DuplicateKeys,
+    /// How many arrays and objects can be nested inside each other, 128 by default. Each level
+    /// takes stack space, so a very high limit can overflow the stack on deep input.
+    pub max_depth: usize,
+    /// The longest string or key allowed, in bytes after decoding escapes.
+    pub max_string_bytes: Option<usize>,
+    /// The most elements an array or members an object can have.
+    pub max_members: Option<usize>,
+    /// The longest input accepted, in bytes.
+    pub max_input_bytes: Option<usize>,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            duplicate_keys: DuplicateKeys::default(),
+            max_depth: 128,
+            max_string_bytes: None,
+            max_members: None,
+            max_input_bytes: None,
+        }
+    }
+}
@@ -0,0 +1,658 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    DuplicateKeys, JsonError, JsonErrorKind, JsonMap, JsonValue, ParserOptions, TextPosition, Token,
+};
+
+/// Single pass recursive descent parser, building the value tree straight from the input.
+///
+/// The lexing functions are shared with `tokenize_json_string` through `next_token`, so both
+/// agree on what a string, number or literal is.
+pub(crate) struct Parser<'a> {
+    json_string: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    options: ParserOptions,
+    depth: usize,
+    // Where `json_string` starts in the whole input, the streaming parsers hand over one
+    // piece of it at a time
+    base: TextPosition,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(json_string: &'a str) -> Parser<'a> {
+        Parser::with_options(json_string, ParserOptions::default())
+    }
+
+    pub(crate) fn with_options(json_string: &'a str, options: ParserOptions) -> Parser<'a> {
+        Parser::with_base(json_string, TextPosition::START, options)
+    }
+
+    pub(crate) fn with_base(
+        json_string: &'a str,
+        base: TextPosition,
+        options: ParserOptions,
+    ) -> Parser<'a> {
+        Parser {
+            json_string,
+            bytes: json_string.as_bytes(),
+            pos: 0,
+            options,
+            depth: 0,
+            base,
+        }
+    }
+
+    /// How far into `json_string` the parser has read.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Parses a whole document, which has to be exactly one value surrounded by whitespace.
+    pub(crate) fn parse_document(&mut self) -> Result<JsonValue, JsonError> {
+        self.check_input_size()?;
+        let value = self.parse_value(false)?;
+
+        self.skip_whitespace();
+        if self.pos < self.bytes.len() {
+            return Err(self.error(JsonErrorKind::TrailingData, self.pos));
+        }
+
+        Ok(value)
+    }
+
+    /// Parses the value at the start of the input, after any whitespace, and returns it along
+    /// with the input that follows it.
+    pub(crate) fn parse_prefix(&mut self) -> Result<(JsonValue, &'a str), JsonError> {
+        self.check_input_size()?;
+        let value = self.parse_value(false)?;
+
+        Ok((value, &self.json_string[self.pos..]))
+    }
+
+    /// Reads the next token along with the byte offset it starts at, or None at the end of the
+    /// input. No grammar checks are made beyond the token itself.
+    pub(crate) fn next_token(&mut self) -> Result<Option<(usize, Token)>, JsonError> {
+        self.skip_whitespace();
+        let pos = self.pos;
+
+        let token = match self.peek() {
+            None => return Ok(None),
+            Some(b'{') => {
+                self.pos += 1;
+                Token::JsonObjBeg
+            }
+            Some(b'}') => {
+                self.pos += 1;
+                Token::JsonObjEnd
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                Token::JsonArrBeg
+            }
+            Some(b']') => {
+                self.pos += 1;
+                Token::JsonArrEnd
+            }
+            Some(b',') => {
+                self.pos += 1;
+                Token::JsonComma
+            }
+            Some(b':') => {
+                self.pos += 1;
+                Token::JsonColon
+            }
+            Some(b'"') => {
+                let content = self.parse_string()?;
+
+                // A string is a key when the next non-whitespace character is a colon
+                self.skip_whitespace();
+                if self.peek() == Some(b':') {
+                    Token::JsonKey(content)
+                } else {
+                    Token::JsonString(content)
+                }
+            }
+            Some(b) if starts_number(b) => Token::JsonNum(self.parse_number()?),
+            Some(b) if b.is_ascii_alphabetic() => self
+                .parse_literal()?
+                .map_or(Token::JsonNull, Token::JsonBool),
+            Some(_) => return Err(self.unexpected_character()),
+        };
+
+        Ok(Some((pos, token)))
+    }
+
+    // `nested` tells whether the value is inside a container, a stray closing bracket is
+    // reported as a missing value there and as an unexpected character at the top level
+    fn parse_value(&mut self, nested: bool) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            None => Err(self.error(JsonErrorKind::UnexpectedEnd, self.pos)),
+            Some(b'{' | b'[') => self.parse_container(),
+            Some(b'"') => Ok(JsonValue::JsonString(self.parse_string()?)),
+            Some(b) if starts_number(b) => Ok(JsonValue::JsonNum(self.parse_number()?)),
+            Some(b) if b.is_ascii_alphabetic() => Ok(self
+                .parse_literal()?
+                .map_or(JsonValue::JsonNull, JsonValue::JsonBool)),
+            Some(b']' | b'}') if !nested => Err(self.unexpected_character()),
+            Some(b']' | b'}' | b',' | b':') => {
+                Err(self.error(JsonErrorKind::ExpectedValue, self.pos))
+            }
+            Some(_) => Err(self.unexpected_character()),
+        }
+    }
+
+    fn check_input_size(&self) -> Result<(), JsonError> {
+        match self.options.max_input_bytes {
+            Some(max) if self.bytes.len() > max => Err(self.error(JsonErrorKind::InputTooLarge, 0)),
+            _ => Ok(()),
+        }
+    }
+
+    // Parses the array or object at the current position, keeping track of how deep it is
+    fn parse_container(&mut self) -> Result<JsonValue, JsonError> {
+        if self.depth >= self.options.max_depth {
+            return Err(self.error(JsonErrorKind::TooDeep, self.pos));
+        }
+
+        self.depth += 1;
+        let value = if self.peek() == Some(b'{') {
+            self.parse_object()
+        } else {
+            self.parse_array()
+        };
+        self.depth -= 1;
+
+        value
+    }
+
+    // Errors if the container opened at `start` already has as many members as allowed
+    fn check_members(&self, members: usize, start: usize) -> Result<(), JsonError> {
+        match self.options.max_members {
+            Some(max) if members >= max => Err(self.error(JsonErrorKind::TooManyMembers, start)),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let mut j_vec: Vec<JsonValue> = Vec::new();
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b']') => {
+                self.pos += 1;
+                return Ok(JsonValue::JsonArr(j_vec));
+            }
+            Some(b'}') => return Err(self.unexpected_character()),
+            _ => (),
+        }
+
+        loop {
+            self.check_members(j_vec.len(), start)?;
+            j_vec.push(self.parse_nested_value(start)?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+
+                    if let Some(b']' | b'}') = self.peek() {
+                        return Err(self.error(JsonErrorKind::TrailingComma, self.pos));
+                    }
+                }
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::JsonArr(j_vec));
+                }
+                _ => return Err(self.missing_comma(start)),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let mut j_map: JsonMap = JsonMap::new();
+        let mut keys = SeenKeys::default();
+        // Counted apart from the map, which holds fewer members when keys repeat
+        let mut members = 0;
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'}') => {
+                self.pos += 1;
+                return Ok(JsonValue::JsonObj(j_map));
+            }
+            Some(b']') => return Err(self.unexpected_character()),
+            _ => (),
+        }
+
+        loop {
+            self.check_members(members, start)?;
+            members += 1;
+
+            self.skip_whitespace();
+            let key_pos = self.pos;
+            let key = match self.peek() {
+                Some(b'"') => self.parse_string()?,
+                // The opening brace has been handled, so this can only follow a comma
+                Some(b'}' | b']') => return Err(self.error(JsonErrorKind::TrailingComma, self.pos)),
+                Some(b) if starts_token(b) || b == b',' || b == b':' => {
+                    return Err(self.error(JsonErrorKind::ExpectedKey, self.pos))
+                }
+                None => return Err(self.error(JsonErrorKind::UnclosedContainer, start)),
+                Some(_) => return Err(self.unexpected_character()),
+            };
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b':') => self.pos += 1,
+                Some(b) if starts_token(b) || matches!(b, b',' | b'}' | b']') => {
+                    return Err(self.error(JsonErrorKind::MissingColon, self.pos))
+                }
+                None => return Err(self.error(JsonErrorKind::UnclosedContainer, start)),
+                Some(_) => return Err(self.unexpected_character()),
+            }
+
+            let value = self.parse_nested_value(start)?;
+            insert_member(
+                self.options.duplicate_keys,
+                &mut j_map,
+                &mut keys,
+                key,
+                key_pos,
+                value,
+            )
+            .map_err(|(key, first_offset)| {
+                self.duplicate_key(key, self.position(first_offset), key_pos)
+            })?;
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::JsonObj(j_map));
+                }
+                _ => return Err(self.missing_comma(start)),
+            }
+        }
+    }
+
+    // Parses a value inside the container opened at `start`, running out of input there means
+    // the container was never closed
+    fn parse_nested_value(&mut self, start: usize) -> Result<JsonValue, JsonError> {
+        self.parse_value(true).map_err(|error| {
+            if *error.kind() == JsonErrorKind::UnexpectedEnd {
+                self.error(JsonErrorKind::UnclosedContainer, start)
+            } else {
+                error
+            }
+        })
+    }
+
+    // Error for whatever stands where a comma or the end of the container opened at `start`
+    // was expected
+    fn missing_comma(&self, start: usize) -> JsonError {
+        match self.peek() {
+            None => self.error(JsonErrorKind::UnclosedContainer, start),
+            Some(b) if starts_token(b) => self.error(JsonErrorKind::MissingComma, self.pos),
+            Some(_) => self.unexpected_character(),
+        }
+    }
+
+    // Reads a string starting at its opening quote, decoding escape sequences on the way.
+    // Unterminated strings, unknown escapes, lone surrogates and raw control characters are
+    // all rejected, as RFC 8259 requires
+    pub(crate) fn parse_string(&mut self) -> Result<String, JsonError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let mut content = String::new();
+
+        loop {
+            // Copy the run of characters that need no decoding in one go
+            let run_start = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            content.push_str(&self.json_string[run_start..self.pos]);
+
+            // Every escape is followed by another run, so this also sees decoded characters
+            if let Some(max) = self.options.max_string_bytes {
+                if content.len() > max {
+                    return Err(self.error(JsonErrorKind::StringTooLong, start));
+                }
+            }
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(content);
+                }
+                Some(b'\\') => content.push(self.parse_escape()?),
+                Some(_) => return Err(self.error(JsonErrorKind::ControlCharacter, self.pos)),
+                None => return Err(self.error(JsonErrorKind::UnterminatedString, start)),
+            }
+        }
+    }
+
+    // Decodes the escape sequence starting at the current backslash
+    fn parse_escape(&mut self) -> Result<char, JsonError> {
+        let start = self.pos;
+        let invalid_escape = |parser: &Self| parser.error(JsonErrorKind::InvalidEscape, start);
+        let lone_surrogate = |parser: &Self| parser.error(JsonErrorKind::LoneSurrogate, start);
+
+        // Every byte looked at is consumed, so running out of input leaves `pos` at the end
+        self.pos += 1;
+        let escaped = match self.peek() {
+            Some(b) => b,
+            None => return Err(invalid_escape(self)),
+        };
+        self.pos += 1;
+
+        match escaped {
+            b'"' => Ok('"'),
+            b'\\' => Ok('\\'),
+            b'/' => Ok('/'),
+            b'b' => Ok('\u{08}'),
+            b'f' => Ok('\u{0C}'),
+            b'n' => Ok('\n'),
+            b'r' => Ok('\r'),
+            b't' => Ok('\t'),
+            b'u' => {
+                let code_unit = self
+                    .parse_hex_code_unit()
+                    .ok_or_else(|| invalid_escape(self))?;
+
+                match code_unit {
+                    // High surrogate, has to be followed by an escaped low surrogate
+                    0xD800..=0xDBFF => {
+                        for expected in [b'\\', b'u'] {
+                            if self.peek() != Some(expected) {
+                                return Err(lone_surrogate(self));
+                            }
+                            self.pos += 1;
+                        }
+
+                        let low = self
+                            .parse_hex_code_unit()
+                            .ok_or_else(|| invalid_escape(self))?;
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Err(lone_surrogate(self));
+                        }
+
+                        char::from_u32(0x10000 + ((code_unit - 0xD800) << 10) + (low - 0xDC00))
+                            .ok_or_else(|| lone_surrogate(self))
+                    }
+                    // Low surrogate without a high surrogate before it
+                    0xDC00..=0xDFFF => Err(lone_surrogate(self)),
+                    _ => char::from_u32(code_unit).ok_or_else(|| invalid_escape(self)),
+                }
+            }
+            _ => Err(invalid_escape(self)),
+        }
+    }
+
+    fn parse_hex_code_unit(&mut self) -> Option<u32> {
+        let mut code_unit = 0;
+
+        for _ in 0..4 {
+            let digit = (*self.bytes.get(self.pos)? as char).to_digit(16)?;
+            code_unit = code_unit * 16 + digit;
+            self.pos += 1;
+        }
+
+        Some(code_unit)
+    }
+
+    pub(crate) fn parse_number(&mut self) -> Result<f64, JsonError> {
+        let start = self.pos;
+
+        // Take the whole run of characters that could belong to a number, the grammar check
+        // below decides whether the run is actually a valid JSON number. Letters are taken as
+        // well so that e.g. 0x10 is reported as one bad number
+        while let Some(b) = self.peek() {
+            if !is_json_number_byte(b) && !b.is_ascii_alphanumeric() {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        let number = &self.json_string[start..self.pos];
+        match number.parse::<f64>() {
+            // Infinity would be written back as null
+            Ok(num) if is_valid_json_number(number) && num.is_infinite() => {
+                Err(self.error(JsonErrorKind::NumberOutOfRange, start))
+            }
+            Ok(num) if is_valid_json_number(number) => Ok(num),
+            _ => Err(self.error(JsonErrorKind::InvalidNumber, start)),
+        }
+    }
+
+    // Reads true, false or null, the latter being None
+    pub(crate) fn parse_literal(&mut self) -> Result<Option<bool>, JsonError> {
+        let start = self.pos;
+
+        while let Some(b) = self.peek() {
+            if !b.is_ascii_alphabetic() {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        match &self.json_string[start..self.pos] {
+            "true" => Ok(Some(true)),
+            "false" => Ok(Some(false)),
+            "null" => Ok(None),
+            // Any other bare word is not a JSON literal
+            _ => Err(self.error(JsonErrorKind::InvalidLiteral, start)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn unexpected_character(&self) -> JsonError {
+        match self.json_string[self.pos..].chars().next() {
+            Some(ch) => self.error(JsonErrorKind::UnexpectedCharacter(ch), self.pos),
+            None => self.error(JsonErrorKind::UnexpectedEnd, self.pos),
+        }
+    }
+
+    fn duplicate_key(&self, key: String, first: TextPosition, key_pos: usize) -> JsonError {
+        self.error(duplicate_key_kind(key, first), key_pos)
+    }
+
+    fn position(&self, offset: usize) -> TextPosition {
+        self.base.advance(&self.json_string[..offset])
+    }
+
+    fn error(&self, kind: JsonErrorKind, offset: usize) -> JsonError {
+        JsonError::at(kind, self.position(offset))
+    }
+}
+
+// Parses a document given as bytes that starts at `base` in the whole input
+pub(crate) fn parse_bytes(
+    json_bytes: &[u8],
+    base: TextPosition,
+    options: &ParserOptions,
+) -> Result<JsonValue, JsonError> {
+    let error = match std::str::from_utf8(json_bytes) {
+        Ok(json_string) => {
+            return Parser::with_base(json_string, base, options.clone()).parse_document()
+        }
+        Err(error) => error,
+    };
+
+    if let Some(max) = options.max_input_bytes {
+        if json_bytes.len() > max {
+            return Err(JsonError::at(JsonErrorKind::InputTooLarge, base));
+        }
+    }
+
+    // The valid part is parsed on its own. Running into its end means the parser wanted to
+    // read on, into the invalid bytes
+    let json_string = std::str::from_utf8(&json_bytes[..error.valid_up_to()]).unwrap_or_default();
+    let mut parser = Parser::with_base(json_string, base, options.clone());
+    let result = parser.parse_document();
+
+    if parser.pos() == json_string.len() {
+        return Err(JsonError::at(
+            JsonErrorKind::InvalidUtf8,
+            base.advance(json_string),
+        ));
+    }
+    result
+}
+
+pub(crate) fn duplicate_key_kind(key: String, first: TextPosition) -> JsonErrorKind {
+    JsonErrorKind::DuplicateKey {
+        key,
+        first_offset: first.offset,
+        first_line: first.line,
+        first_column: first.column,
+    }
+}
+
+/// Adds a member to an object being parsed, following the duplicate key policy. A key the
+/// policy rejects is handed back along with where it was first seen, `P` being whatever the
+/// parser uses for positions.
+pub(crate) fn insert_member<P: Copy>(
+    policy: DuplicateKeys,
+    j_map: &mut JsonMap,
+    keys: &mut SeenKeys<P>,
+    key: String,
+    key_pos: P,
+    value: JsonValue,
+) -> Result<(), (String, P)> {
+    match policy {
+        DuplicateKeys::LastWins => {
+            j_map.insert(key, value);
+        }
+        DuplicateKeys::FirstWins => {
+            if !j_map.contains_key(&key) {
+                j_map.insert(key, value);
+            }
+        }
+        DuplicateKeys::Error => match keys.first_positions.get(&key) {
+            Some(&first) => return Err((key, first)),
+            None => {
+                keys.first_positions.insert(key.clone(), key_pos);
+                j_map.insert(key, value);
+            }
+        },
+        DuplicateKeys::CollectAll => match j_map.get_mut(&key) {
+            // The first repeat turns the value into an array, later ones add to it
+            Some(JsonValue::JsonArr(values)) if keys.collected.contains(&key) => values.push(value),
+            Some(first) => {
+                let first = std::mem::replace(first, JsonValue::JsonNull);
+                j_map.insert(key.clone(), JsonValue::JsonArr(vec![first, value]));
+                keys.collected.insert(key);
+            }
+            None => {
+                j_map.insert(key, value);
+            }
+        },
+    }
+
+    Ok(())
+}
+
+// What an object's duplicate key policy needs to remember about the keys seen so far
+pub(crate) struct SeenKeys<P> {
+    first_positions: HashMap<String, P>,
+    collected: HashSet<String>,
+}
+
+impl<P> Default for SeenKeys<P> {
+    fn default() -> Self {
+        SeenKeys {
+            first_positions: HashMap::new(),
+            collected: HashSet::new(),
+        }
+    }
+}
+
+// Bytes that can start a token other than a separator or a closing bracket
+pub(crate) fn starts_token(b: u8) -> bool {
+    matches!(b, b'"' | b'{' | b'[') || starts_number(b) || b.is_ascii_alphabetic()
+}
+
+// Bytes that can start a JSON number. Other bytes of a number, like `+` or `e`, are left to be
+// reported as unexpected characters or invalid literals
+pub(crate) fn starts_number(b: u8) -> bool {
+    b == b'-' || b.is_ascii_digit()
+}
+
+pub(crate) fn is_json_number_byte(b: u8) -> bool {
+    b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E')
+}
+
+// Checks a number against the RFC 8259 grammar:
+// number = [ minus ] int [ frac ] [ exp ]
+// int = zero / ( digit1-9 *DIGIT )
+// frac = decimal-point 1*DIGIT
+// exp = e [ minus / plus ] 1*DIGIT
+fn is_valid_json_number(number: &str) -> bool {
+    let bytes = number.as_bytes();
+    let mut i = 0;
+
+    let count_digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+
+    match bytes.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => i += count_digits(i),
+        _ => return false,
+    }
+
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        let digits = count_digits(i);
+        if digits == 0 {
+            return false;
+        }
+        i += digits;
+    }
+
+    if let Some(b'e' | b'E') = bytes.get(i) {
+        i += 1;
+        if let Some(b'-' | b'+') = bytes.get(i) {
+            i += 1;
+        }
+        let digits = count_digits(i);
+        if digits == 0 {
+            return false;
+        }
+        i += digits;
+    }
+
+    i == bytes.len()
+}
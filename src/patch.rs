@@ -0,0 +1,707 @@
+use std::fmt;
+
+use crate::{pointer::Pointer, JsonMap, JsonValue, PointerError};
+
+// Largest table the longest common subsequence of two arrays is worked out with, past it the
+// elements are compared by index
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// Why a JSON Patch could not be read or applied.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum PatchErrorKind {
+    /// A patch that isn't an array of operations.
+    NotAnArray,
+    /// An operation that isn't an object.
+    NotAnObject,
+    /// An operation without a member it needs, like `value` for `add`.
+    MissingMember(&'static str),
+    /// An `op`, `path` or `from` member that isn't a string.
+    InvalidMember(&'static str),
+    UnknownOp(String),
+    /// A `path` or `from` that isn't a valid pointer, or can't be followed in the document.
+    Pointer(PointerError),
+    /// A `test` operation whose value differs from the one in the document.
+    TestFailed,
+    /// A `move` of a value into one of its own children.
+    MoveIntoChild,
+}
+
+impl fmt::Display for PatchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchErrorKind::NotAnArray => write!(f, "patch is not an array"),
+            PatchErrorKind::NotAnObject => write!(f, "operation is not an object"),
+            PatchErrorKind::MissingMember(name) => write!(f, "missing member {:?}", name),
+            PatchErrorKind::InvalidMember(name) => write!(f, "member {:?} is not a string", name),
+            PatchErrorKind::UnknownOp(op) => write!(f, "unknown op {:?}", op),
+            PatchErrorKind::Pointer(error) => write!(f, "{}", error),
+            PatchErrorKind::TestFailed => write!(f, "test failed"),
+            PatchErrorKind::MoveIntoChild => write!(f, "cannot move a value into its own child"),
+        }
+    }
+}
+
+/// An error reading or applying a JSON Patch, along with the operation it happened at.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PatchError {
+    kind: PatchErrorKind,
+    operation: usize,
+}
+
+impl PatchError {
+    pub fn kind(&self) -> &PatchErrorKind {
+        &self.kind
+    }
+
+    /// Index of the operation that failed, 0 when the patch isn't an array.
+    pub fn operation(&self) -> usize {
+        self.operation
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in operation {}", self.kind, self.operation)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// One operation of a JSON Patch. Paths are JSON Pointers, see `JsonValue::pointer`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchOperation {
+    /// Sets an object member, or inserts an array element before the one at the index.
+    Add {
+        path: String,
+        value: JsonValue,
+    },
+    Remove {
+        path: String,
+    },
+    /// Replaces a value that exists.
+    Replace {
+        path: String,
+        value: JsonValue,
+    },
+    /// Removes the value at `from` and adds it at `path`.
+    Move {
+        from: String,
+        path: String,
+    },
+    /// Adds a copy of the value at `from` at `path`.
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Fails the patch unless the value at `path` equals `value`.
+    Test {
+        path: String,
+        value: JsonValue,
+    },
+}
+
+impl PatchOperation {
+    fn op(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    fn from_value(operation: &JsonValue) -> Result<PatchOperation, PatchErrorKind> {
+        let JsonValue::JsonObj(map) = operation else {
+            return Err(PatchErrorKind::NotAnObject);
+        };
+
+        let string = |name: &'static str| match map.get(name) {
+            Some(JsonValue::JsonString(string)) => Ok(string.clone()),
+            Some(_) => Err(PatchErrorKind::InvalidMember(name)),
+            None => Err(PatchErrorKind::MissingMember(name)),
+        };
+        let pointer = |name: &'static str| {
+            let pointer = string(name)?;
+            Pointer::parse(&pointer).map_err(PatchErrorKind::Pointer)?;
+            Ok(pointer)
+        };
+        let value = || {
+            map.get("value")
+                .cloned()
+                .ok_or(PatchErrorKind::MissingMember("value"))
+        };
+
+        let op = string("op")?;
+        let path = pointer("path")?;
+        match op.as_str() {
+            "add" => Ok(PatchOperation::Add {
+                path,
+                value: value()?,
+            }),
+            "remove" => Ok(PatchOperation::Remove { path }),
+            "replace" => Ok(PatchOperation::Replace {
+                path,
+                value: value()?,
+            }),
+            "move" => Ok(PatchOperation::Move {
+                from: pointer("from")?,
+                path,
+            }),
+            "copy" => Ok(PatchOperation::Copy {
+                from: pointer("from")?,
+                path,
+            }),
+            "test" => Ok(PatchOperation::Test {
+                path,
+                value: value()?,
+            }),
+            _ => Err(PatchErrorKind::UnknownOp(op)),
+        }
+    }
+
+    fn to_value(&self) -> JsonValue {
+        let mut map = JsonMap::new();
+        map.insert(String::from("op"), JsonValue::from(self.op()));
+
+        match self {
+            PatchOperation::Add { path, value }
+            | PatchOperation::Replace { path, value }
+            | PatchOperation::Test { path, value } => {
+                map.insert(String::from("path"), JsonValue::from(path.as_str()));
+                map.insert(String::from("value"), value.clone());
+            }
+            PatchOperation::Remove { path } => {
+                map.insert(String::from("path"), JsonValue::from(path.as_str()));
+            }
+            PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+                map.insert(String::from("from"), JsonValue::from(from.as_str()));
+                map.insert(String::from("path"), JsonValue::from(path.as_str()));
+            }
+        }
+
+        JsonValue::JsonObj(map)
+    }
+
+    fn apply(&self, document: &mut JsonValue) -> Result<(), PatchErrorKind> {
+        let pointer = |pointer: &str| Pointer::parse(pointer).map_err(PatchErrorKind::Pointer);
+
+        match self {
+            PatchOperation::Add { path, value } => {
+                pointer(path)?
+                    .insert(document, value.clone())
+                    .map_err(PatchErrorKind::Pointer)?;
+            }
+            PatchOperation::Remove { path } => {
+                pointer(path)?
+                    .remove(document)
+                    .map_err(PatchErrorKind::Pointer)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                pointer(path)?
+                    .replace(document, value.clone())
+                    .map_err(PatchErrorKind::Pointer)?;
+            }
+            PatchOperation::Move { from, path } => {
+                let from_pointer = pointer(from)?;
+                if from == path {
+                    from_pointer
+                        .get(document)
+                        .map_err(PatchErrorKind::Pointer)?;
+                    return Ok(());
+                }
+                if path
+                    .strip_prefix(from.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+                {
+                    return Err(PatchErrorKind::MoveIntoChild);
+                }
+
+                let to_pointer = pointer(path)?;
+                let moved = from_pointer
+                    .remove(document)
+                    .map_err(PatchErrorKind::Pointer)?;
+                to_pointer
+                    .insert(document, moved)
+                    .map_err(PatchErrorKind::Pointer)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let copied = pointer(from)?
+                    .get(document)
+                    .map_err(PatchErrorKind::Pointer)?
+                    .clone();
+                pointer(path)?
+                    .insert(document, copied)
+                    .map_err(PatchErrorKind::Pointer)?;
+            }
+            PatchOperation::Test { path, value } => {
+                let found = pointer(path)?
+                    .get(document)
+                    .map_err(PatchErrorKind::Pointer)?;
+                if found != value {
+                    return Err(PatchErrorKind::TestFailed);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A JSON Patch (RFC 6902), a list of operations applied in order with
+/// `JsonValue::apply_patch`.
+///
+/// ```
+/// use json_reader::{parse_json_string, JsonPatch};
+///
+/// let mut config = parse_json_string(r#"{"port": 80, "hosts": ["a"]}"#)?;
+/// let patch = JsonPatch::from_value(&parse_json_string(
+///     r#"[{"op": "replace", "path": "/port", "value": 8080},
+///         {"op": "add", "path": "/hosts/-", "value": "b"}]"#,
+/// )?)?;
+///
+/// config.apply_patch(&patch)?;
+/// assert_eq!(parse_json_string(r#"{"port": 8080, "hosts": ["a", "b"]}"#)?, config);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct JsonPatch {
+    operations: Vec<PatchOperation>,
+}
+
+impl JsonPatch {
+    pub fn new(operations: Vec<PatchOperation>) -> JsonPatch {
+        JsonPatch { operations }
+    }
+
+    /// Reads a patch from its JSON form, an array of operation objects. Members an operation
+    /// doesn't use are ignored.
+    pub fn from_value(patch: &JsonValue) -> Result<JsonPatch, PatchError> {
+        let JsonValue::JsonArr(operations) = patch else {
+            return Err(PatchError {
+                kind: PatchErrorKind::NotAnArray,
+                operation: 0,
+            });
+        };
+
+        let operations = operations
+            .iter()
+            .enumerate()
+            .map(|(i, operation)| {
+                PatchOperation::from_value(operation)
+                    .map_err(|kind| PatchError { kind, operation: i })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(JsonPatch { operations })
+    }
+
+    /// The JSON form of the patch.
+    pub fn to_value(&self) -> JsonValue {
+        JsonValue::JsonArr(
+            self.operations
+                .iter()
+                .map(PatchOperation::to_value)
+                .collect(),
+        )
+    }
+
+    /// A patch that turns `from` into `to`.
+    ///
+    /// Object members are compared by name, and arrays by their longest common subsequence, so
+    /// that inserting or removing elements doesn't replace all the ones after them. Values
+    /// that differ are diffed further down when both are objects or both are arrays, and
+    /// replaced otherwise. The patch uses only `add`, `remove` and `replace`.
+    pub fn diff(from: &JsonValue, to: &JsonValue) -> JsonPatch {
+        let mut operations = Vec::new();
+        diff_values(from, to, &mut Vec::new(), &mut operations);
+        JsonPatch { operations }
+    }
+
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.operations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+impl From<Vec<PatchOperation>> for JsonPatch {
+    fn from(operations: Vec<PatchOperation>) -> Self {
+        JsonPatch::new(operations)
+    }
+}
+
+/// Writes the JSON form of the patch.
+impl fmt::Display for JsonPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+impl JsonValue {
+    /// Applies the operations of a JSON Patch in order. If one fails, the value is left as it
+    /// was before the patch.
+    pub fn apply_patch(&mut self, patch: &JsonPatch) -> Result<(), PatchError> {
+        let mut patched = self.clone();
+        for (i, operation) in patch.operations.iter().enumerate() {
+            operation
+                .apply(&mut patched)
+                .map_err(|kind| PatchError { kind, operation: i })?;
+        }
+
+        *self = patched;
+        Ok(())
+    }
+}
+
+fn diff_values(
+    from: &JsonValue,
+    to: &JsonValue,
+    path: &mut Vec<String>,
+    operations: &mut Vec<PatchOperation>,
+) {
+    if from == to {
+        return;
+    }
+
+    match (from, to) {
+        (JsonValue::JsonObj(from), JsonValue::JsonObj(to)) => {
+            for key in from.keys() {
+                if !to.contains_key(key) {
+                    path.push(key.clone());
+                    operations.push(PatchOperation::Remove {
+                        path: Pointer::format(path),
+                    });
+                    path.pop();
+                }
+            }
+
+            for (key, value) in to.iter() {
+                path.push(key.clone());
+                match from.get(key) {
+                    Some(old) => diff_values(old, value, path, operations),
+                    None => operations.push(PatchOperation::Add {
+                        path: Pointer::format(path),
+                        value: value.clone(),
+                    }),
+                }
+                path.pop();
+            }
+        }
+        (JsonValue::JsonArr(from), JsonValue::JsonArr(to)) => {
+            diff_arrays(from, to, path, operations)
+        }
+        _ => operations.push(PatchOperation::Replace {
+            path: Pointer::format(path),
+            value: to.clone(),
+        }),
+    }
+}
+
+// A step from the old elements of an array to the new ones
+enum Edit {
+    Keep,
+    // The old element at the first index becomes the new one at the second
+    Change(usize, usize),
+    Remove,
+    Insert(usize),
+}
+
+fn diff_arrays(
+    from: &[JsonValue],
+    to: &[JsonValue],
+    path: &mut Vec<String>,
+    operations: &mut Vec<PatchOperation>,
+) {
+    let prefix = from.iter().zip(to).take_while(|(a, b)| a == b).count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let from = &from[prefix..from.len() - suffix];
+    let to = &to[prefix..to.len() - suffix];
+
+    let edits = if from.len().saturating_mul(to.len()) <= MAX_LCS_CELLS {
+        lcs_edits(from, to)
+    } else {
+        index_edits(from.len(), to.len())
+    };
+
+    // Where the next element is in the array as patched so far
+    let mut index = prefix;
+    for edit in edits {
+        path.push(index.to_string());
+        match edit {
+            Edit::Keep => index += 1,
+            Edit::Change(i, j) => {
+                diff_values(&from[i], &to[j], path, operations);
+                index += 1;
+            }
+            Edit::Remove => operations.push(PatchOperation::Remove {
+                path: Pointer::format(path),
+            }),
+            Edit::Insert(j) => {
+                operations.push(PatchOperation::Add {
+                    path: Pointer::format(path),
+                    value: to[j].clone(),
+                });
+                index += 1;
+            }
+        }
+        path.pop();
+    }
+}
+
+// Keeps the longest common subsequence of the elements, and changes an old element into a
+// new one where that keeps as many
+fn lcs_edits(from: &[JsonValue], to: &[JsonValue]) -> Vec<Edit> {
+    let columns = to.len() + 1;
+    // lengths[i * columns + j] is the length of the longest common subsequence of from[i..]
+    // and to[j..]
+    let mut lengths = vec![0u32; (from.len() + 1) * columns];
+    for i in (0..from.len()).rev() {
+        for j in (0..to.len()).rev() {
+            lengths[i * columns + j] = if from[i] == to[j] {
+                lengths[(i + 1) * columns + j + 1] + 1
+            } else {
+                lengths[(i + 1) * columns + j].max(lengths[i * columns + j + 1])
+            };
+        }
+    }
+    let length = |i: usize, j: usize| lengths[i * columns + j];
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < from.len() || j < to.len() {
+        if i == from.len() {
+            edits.push(Edit::Insert(j));
+            j += 1;
+        } else if j == to.len() {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else if from[i] == to[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if length(i + 1, j + 1) == length(i, j) {
+            edits.push(Edit::Change(i, j));
+            i += 1;
+            j += 1;
+        } else if length(i + 1, j) >= length(i, j + 1) {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+// Changes the elements at the same index, then removes or inserts the rest
+fn index_edits(from_len: usize, to_len: usize) -> Vec<Edit> {
+    let common = from_len.min(to_len);
+    let mut edits: Vec<Edit> = (0..common).map(|i| Edit::Change(i, i)).collect();
+    edits.extend((common..from_len).map(|_| Edit::Remove));
+    edits.extend((common..to_len).map(Edit::Insert));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{parse_json_string, PointerErrorKind};
+
+    fn json(json_string: &str) -> JsonValue {
+        parse_json_string(json_string).unwrap()
+    }
+
+    fn patch(json_string: &str) -> JsonPatch {
+        JsonPatch::from_value(&json(json_string)).unwrap()
+    }
+
+    #[test]
+    fn applies_every_kind_of_operation() {
+        let mut document = json(r#"{"a": {"b": [1, 2]}, "c": "x"}"#);
+        document
+            .apply_patch(&patch(
+                r#"[
+                    {"op": "add", "path": "/a/b/1", "value": 5},
+                    {"op": "remove", "path": "/a/b/0"},
+                    {"op": "replace", "path": "/c", "value": null},
+                    {"op": "copy", "from": "/a/b", "path": "/d"},
+                    {"op": "move", "from": "/a", "path": "/e"},
+                    {"op": "test", "path": "/e/b", "value": [5, 2]}
+                ]"#,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            json(r#"{"c": null, "d": [5, 2], "e": {"b": [5, 2]}}"#),
+            document
+        );
+    }
+
+    #[test]
+    fn leaves_the_document_unchanged_when_an_operation_fails() {
+        let original = json(r#"{"a": 1, "b": [true]}"#);
+        let mut document = original.clone();
+
+        let error = document
+            .apply_patch(&patch(
+                r#"[
+                    {"op": "add", "path": "/c", "value": 3},
+                    {"op": "remove", "path": "/b/0"},
+                    {"op": "test", "path": "/a", "value": 2}
+                ]"#,
+            ))
+            .unwrap_err();
+        assert_eq!(
+            (&PatchErrorKind::TestFailed, 2),
+            (error.kind(), error.operation())
+        );
+        assert_eq!(original, document);
+
+        let error = document
+            .apply_patch(&patch(r#"[{"op": "move", "from": "/b", "path": "/b/0"}]"#))
+            .unwrap_err();
+        assert_eq!(&PatchErrorKind::MoveIntoChild, error.kind());
+
+        let error = document
+            .apply_patch(&patch(r#"[{"op": "add", "path": "/b/2", "value": 0}]"#))
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            PatchErrorKind::Pointer(error)
+                if *error.kind() == PointerErrorKind::IndexOutOfBounds { index: 2, len: 1 }
+        ));
+        assert_eq!(original, document);
+    }
+
+    #[test]
+    fn rejects_malformed_patches() {
+        let cases = [
+            (r#"{"op": "add"}"#, PatchErrorKind::NotAnArray, 0),
+            (r#"[1]"#, PatchErrorKind::NotAnObject, 0),
+            (
+                r#"[{"op": "test", "path": "", "value": 1}, {"path": "/a"}]"#,
+                PatchErrorKind::MissingMember("op"),
+                1,
+            ),
+            (
+                r#"[{"op": "add", "path": 1, "value": 1}]"#,
+                PatchErrorKind::InvalidMember("path"),
+                0,
+            ),
+            (
+                r#"[{"op": "add", "path": "/a"}]"#,
+                PatchErrorKind::MissingMember("value"),
+                0,
+            ),
+            (
+                r#"[{"op": "copy", "path": "/a"}]"#,
+                PatchErrorKind::MissingMember("from"),
+                0,
+            ),
+            (
+                r#"[{"op": "delete", "path": "/a"}]"#,
+                PatchErrorKind::UnknownOp(String::from("delete")),
+                0,
+            ),
+        ];
+
+        for (patch, kind, operation) in cases {
+            let error = JsonPatch::from_value(&json(patch)).unwrap_err();
+            assert_eq!(
+                (&kind, operation),
+                (error.kind(), error.operation()),
+                "{}",
+                patch
+            );
+        }
+
+        let error = JsonPatch::from_value(&json(r#"[{"op": "remove", "path": "a"}]"#)).unwrap_err();
+        assert!(matches!(error.kind(), PatchErrorKind::Pointer(_)));
+    }
+
+    #[test]
+    fn writes_patches_that_read_back() {
+        let patch_string = r#"[{"op":"add","path":"/a~1b","value":[1]},{"op":"remove","path":"/c"},{"op":"move","from":"/d","path":"/e"}]"#;
+        let patch = patch(patch_string);
+
+        assert_eq!(3, patch.operations().len());
+        assert_eq!(patch_string, patch.to_string());
+        assert_eq!(Ok(patch.clone()), JsonPatch::from_value(&patch.to_value()));
+    }
+
+    #[test]
+    fn diffs_into_small_patches() {
+        let cases = [
+            (r#"{"a": 1}"#, r#"{"a": 1}"#, "[]"),
+            (
+                r#"{"a": 1, "b": {"c": [1, 2]}}"#,
+                r#"{"b": {"c": [1, 3]}, "d": 4}"#,
+                r#"[{"op":"remove","path":"/a"},{"op":"replace","path":"/b/c/1","value":3},{"op":"add","path":"/d","value":4}]"#,
+            ),
+            (
+                "[1, 2, 3, 4]",
+                "[0, 1, 2, 3, 4]",
+                r#"[{"op":"add","path":"/0","value":0}]"#,
+            ),
+            (
+                "[1, 2, 3, 4, 5]",
+                "[1, 3, 4, 6]",
+                r#"[{"op":"remove","path":"/1"},{"op":"replace","path":"/3","value":6}]"#,
+            ),
+            (
+                r#"[{"id": 1, "on": true}, 2]"#,
+                r#"[{"id": 1, "on": false}, 2, 7]"#,
+                r#"[{"op":"replace","path":"/0/on","value":false},{"op":"add","path":"/2","value":7}]"#,
+            ),
+            (
+                r#"{"a": 1}"#,
+                "[1]",
+                r#"[{"op":"replace","path":"","value":[1]}]"#,
+            ),
+        ];
+
+        for (from, to, expected) in cases {
+            let diff = JsonPatch::diff(&json(from), &json(to));
+            assert_eq!(expected, diff.to_string(), "{} -> {}", from, to);
+        }
+    }
+
+    fn arb_small_value() -> impl Strategy<Value = JsonValue> {
+        let leaf = prop_oneof![
+            Just(JsonValue::JsonNull),
+            (0..3u8).prop_map(|n| JsonValue::JsonNum(f64::from(n))),
+            "[ab]".prop_map(JsonValue::JsonString),
+        ];
+
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(JsonValue::JsonArr),
+                prop::collection::vec(("[abc]", inner), 0..4)
+                    .prop_map(|members| JsonValue::JsonObj(members.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn diff_turns_one_value_into_the_other(from in arb_small_value(), to in arb_small_value()) {
+            let diff = JsonPatch::diff(&from, &to);
+            let mut patched = from.clone();
+
+            prop_assert_eq!(Ok(()), patched.apply_patch(&diff));
+            prop_assert_eq!(to, patched);
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use std::{borrow::Cow, fmt};
+
+use crate::JsonValue;
+
+/// Why a JSON Pointer could not be followed.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum PointerErrorKind {
+    /// A pointer that is neither empty nor starts with `/`.
+    NoLeadingSlash,
+    /// A `~` that isn't followed by `0` or `1`.
+    InvalidEscape,
+    /// An object without a member of this name.
+    MissingKey(String),
+    /// A segment into an array that isn't an index, like `01` or `x`.
+    InvalidIndex(String),
+    /// An index past the end of the array. `-` stands for the index right after the last
+    /// element, which is only valid for inserting.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// A segment into a value that is neither an object nor an array.
+    NotAContainer,
+    /// Removing the whole value, which leaves nothing behind.
+    RemoveRoot,
+}
+
+impl fmt::Display for PointerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerErrorKind::NoLeadingSlash => write!(f, "pointer does not start with '/'"),
+            PointerErrorKind::InvalidEscape => write!(f, "'~' not followed by '0' or '1'"),
+            PointerErrorKind::MissingKey(key) => write!(f, "no member named {:?}", key),
+            PointerErrorKind::InvalidIndex(index) => write!(f, "invalid array index {:?}", index),
+            PointerErrorKind::IndexOutOfBounds { index, len } => write!(
+                f,
+                "index {} out of bounds for an array of length {}",
+                index, len
+            ),
+            PointerErrorKind::NotAContainer => write!(f, "value is not an object or array"),
+            PointerErrorKind::RemoveRoot => write!(f, "cannot remove the root value"),
+        }
+    }
+}
+
+/// An error following a JSON Pointer, along with the segment it failed at.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PointerError {
+    kind: PointerErrorKind,
+    segment: usize,
+    path: String,
+}
+
+impl PointerError {
+    pub fn kind(&self) -> &PointerErrorKind {
+        &self.kind
+    }
+
+    /// Index of the segment that failed, starting at 0 for the one after the first `/`.
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+
+    /// The pointer up to and including the segment that failed.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            PointerErrorKind::NoLeadingSlash => write!(f, "{}", self.kind),
+            _ => write!(f, "{} at {:?}", self.kind, self.path),
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// One step of the path to a value, a member name or an array index.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum PathElement {
+    Name(String),
+    Index(usize),
+}
+
+/// The JSON Pointer of a path.
+pub(crate) fn path_pointer(path: &[PathElement]) -> String {
+    let segments: Vec<Cow<str>> = path
+        .iter()
+        .map(|element| match element {
+            PathElement::Name(name) => Cow::Borrowed(name.as_str()),
+            PathElement::Index(index) => Cow::Owned(index.to_string()),
+        })
+        .collect();
+    Pointer::format(&segments)
+}
+
+// A pointer split into its unescaped segments
+pub(crate) struct Pointer {
+    segments: Vec<String>,
+}
+
+impl Pointer {
+    pub(crate) fn parse(pointer: &str) -> Result<Pointer, PointerError> {
+        if pointer.is_empty() {
+            return Ok(Pointer {
+                segments: Vec::new(),
+            });
+        }
+
+        let rest = match pointer.strip_prefix('/') {
+            Some(rest) => rest,
+            None => {
+                return Err(PointerError {
+                    kind: PointerErrorKind::NoLeadingSlash,
+                    segment: 0,
+                    path: String::new(),
+                })
+            }
+        };
+
+        let mut segments = Vec::new();
+        for raw in rest.split('/') {
+            // Decoded in one pass, so that ~01 is ~1 and not /
+            let mut segment = String::with_capacity(raw.len());
+            let mut chars = raw.chars();
+            while let Some(ch) = chars.next() {
+                if ch != '~' {
+                    segment.push(ch);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => segment.push('~'),
+                    Some('1') => segment.push('/'),
+                    _ => {
+                        let parsed = Pointer { segments };
+                        return Err(parsed.error(PointerErrorKind::InvalidEscape, raw));
+                    }
+                }
+            }
+            segments.push(segment);
+        }
+
+        Ok(Pointer { segments })
+    }
+
+    // Error at the segment being parsed, written as `raw`, which follows the parsed ones
+    fn error(&self, kind: PointerErrorKind, raw: &str) -> PointerError {
+        let mut path = Pointer::format(&self.segments);
+        path.push('/');
+        path.push_str(raw);
+
+        PointerError {
+            kind,
+            segment: self.segments.len(),
+            path,
+        }
+    }
+
+    // Error at segment `i` of the pointer
+    fn error_at(&self, kind: PointerErrorKind, i: usize) -> PointerError {
+        PointerError {
+            kind,
+            segment: i,
+            path: Pointer::format(&self.segments[..=i]),
+        }
+    }
+
+    /// Writes segments back as a pointer, escaping `~` and `/`.
+    pub(crate) fn format<S: AsRef<str>>(segments: &[S]) -> String {
+        let mut pointer = String::new();
+        for segment in segments {
+            pointer.push('/');
+            pointer.push_str(&escape_segment(segment.as_ref()));
+        }
+        pointer
+    }
+
+    pub(crate) fn get<'v>(&self, value: &'v JsonValue) -> Result<&'v JsonValue, PointerError> {
+        self.segments
+            .iter()
+            .enumerate()
+            .try_fold(value, |value, (i, segment)| {
+                child(value, segment).map_err(|kind| self.error_at(kind, i))
+            })
+    }
+
+    pub(crate) fn get_mut<'v>(
+        &self,
+        value: &'v mut JsonValue,
+    ) -> Result<&'v mut JsonValue, PointerError> {
+        self.segments
+            .iter()
+            .enumerate()
+            .try_fold(value, |value, (i, segment)| {
+                child_mut(value, segment).map_err(|kind| self.error_at(kind, i))
+            })
+    }
+
+    // The container holding the target and the last segment, None for the root
+    fn parent_mut<'v>(
+        &self,
+        value: &'v mut JsonValue,
+    ) -> Result<Option<(&'v mut JsonValue, &str)>, PointerError> {
+        let Some((last, parents)) = self.segments.split_last() else {
+            return Ok(None);
+        };
+
+        let parent = parents
+            .iter()
+            .enumerate()
+            .try_fold(value, |value, (i, segment)| {
+                child_mut(value, segment).map_err(|kind| self.error_at(kind, i))
+            })?;
+        Ok(Some((parent, last)))
+    }
+
+    /// Adds `new` at the pointer, the way JSON Patch's `add` does: an object member is set,
+    /// and an array element is inserted before the one at the index, or appended for `-`.
+    pub(crate) fn insert(
+        &self,
+        value: &mut JsonValue,
+        new: JsonValue,
+    ) -> Result<Option<JsonValue>, PointerError> {
+        let last = self.segments.len().saturating_sub(1);
+        let (parent, segment) = match self.parent_mut(value)? {
+            Some(parent) => parent,
+            None => return Ok(Some(std::mem::replace(value, new))),
+        };
+
+        match parent {
+            JsonValue::JsonObj(map) => Ok(map.insert(segment.to_string(), new)),
+            JsonValue::JsonArr(vector) => {
+                let index =
+                    array_index(segment, vector.len()).map_err(|kind| self.error_at(kind, last))?;
+                if index > vector.len() {
+                    let len = vector.len();
+                    let kind = PointerErrorKind::IndexOutOfBounds { index, len };
+                    return Err(self.error_at(kind, last));
+                }
+                vector.insert(index, new);
+                Ok(None)
+            }
+            _ => Err(self.error_at(PointerErrorKind::NotAContainer, last)),
+        }
+    }
+
+    /// Replaces the value at the pointer, which has to exist.
+    pub(crate) fn replace(
+        &self,
+        value: &mut JsonValue,
+        new: JsonValue,
+    ) -> Result<JsonValue, PointerError> {
+        Ok(std::mem::replace(self.get_mut(value)?, new))
+    }
+
+    /// Removes the value at the pointer, the elements after an array element move down one.
+    pub(crate) fn remove(&self, value: &mut JsonValue) -> Result<JsonValue, PointerError> {
+        let last = self.segments.len().saturating_sub(1);
+        let (parent, segment) = match self.parent_mut(value)? {
+            Some(parent) => parent,
+            None => {
+                return Err(PointerError {
+                    kind: PointerErrorKind::RemoveRoot,
+                    segment: 0,
+                    path: String::new(),
+                })
+            }
+        };
+
+        match parent {
+            JsonValue::JsonObj(map) => map.remove(segment).ok_or_else(|| {
+                self.error_at(PointerErrorKind::MissingKey(segment.to_string()), last)
+            }),
+            JsonValue::JsonArr(vector) => {
+                let index = existing_index(segment, vector.len())
+                    .map_err(|kind| self.error_at(kind, last))?;
+                Ok(vector.remove(index))
+            }
+            _ => Err(self.error_at(PointerErrorKind::NotAContainer, last)),
+        }
+    }
+}
+
+/// Escapes a key for use as a segment of a JSON Pointer.
+pub(crate) fn escape_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+// The array index a segment stands for, which may be one past the end
+fn array_index(segment: &str, len: usize) -> Result<usize, PointerErrorKind> {
+    if segment == "-" {
+        return Ok(len);
+    }
+
+    // RFC 6901 allows no sign and no leading zeros
+    let is_index = match segment.as_bytes() {
+        [b'0'] => true,
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    };
+    match segment.parse() {
+        Ok(index) if is_index => Ok(index),
+        _ => Err(PointerErrorKind::InvalidIndex(segment.to_string())),
+    }
+}
+
+// The index of an element that exists
+fn existing_index(segment: &str, len: usize) -> Result<usize, PointerErrorKind> {
+    match array_index(segment, len)? {
+        index if index < len => Ok(index),
+        index => Err(PointerErrorKind::IndexOutOfBounds { index, len }),
+    }
+}
+
+fn child<'v>(value: &'v JsonValue, segment: &str) -> Result<&'v JsonValue, PointerErrorKind> {
+    match value {
+        JsonValue::JsonObj(map) => map
+            .get(segment)
+            .ok_or_else(|| PointerErrorKind::MissingKey(segment.to_string())),
+        JsonValue::JsonArr(vector) => Ok(&vector[existing_index(segment, vector.len())?]),
+        _ => Err(PointerErrorKind::NotAContainer),
+    }
+}
+
+fn child_mut<'v>(
+    value: &'v mut JsonValue,
+    segment: &str,
+) -> Result<&'v mut JsonValue, PointerErrorKind> {
+    match value {
+        JsonValue::JsonObj(map) => map
+            .get_mut(segment)
+            .ok_or_else(|| PointerErrorKind::MissingKey(segment.to_string())),
+        JsonValue::JsonArr(vector) => {
+            let index = existing_index(segment, vector.len())?;
+            Ok(&mut vector[index])
+        }
+        _ => Err(PointerErrorKind::NotAContainer),
+    }
+}
+
+/// Access by JSON Pointer (RFC 6901), like `/items/0/name`. The empty pointer is the whole
+/// value, and in a segment `~1` stands for `/` and `~0` for `~`. Errors tell the segment the
+/// pointer failed at.
+impl JsonValue {
+    /// The value the pointer refers to, without cloning anything.
+    pub fn pointer(&self, pointer: &str) -> Result<&JsonValue, PointerError> {
+        Pointer::parse(pointer)?.get(self)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonValue, PointerError> {
+        Pointer::parse(pointer)?.get_mut(self)
+    }
+
+    /// Adds a value like JSON Patch's `add`. An object member is set, returning the value it
+    /// replaced. An array element is inserted at the index, moving the ones after it along,
+    /// and `-` appends. The empty pointer replaces the whole value.
+    pub fn pointer_insert(
+        &mut self,
+        pointer: &str,
+        value: JsonValue,
+    ) -> Result<Option<JsonValue>, PointerError> {
+        Pointer::parse(pointer)?.insert(self, value)
+    }
+
+    /// Replaces the value the pointer refers to, which has to exist, and returns the old one.
+    pub fn pointer_replace(
+        &mut self,
+        pointer: &str,
+        value: JsonValue,
+    ) -> Result<JsonValue, PointerError> {
+        Pointer::parse(pointer)?.replace(self, value)
+    }
+
+    /// Removes the value the pointer refers to and returns it.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<JsonValue, PointerError> {
+        Pointer::parse(pointer)?.remove(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json_string;
+
+    fn json(json_string: &str) -> JsonValue {
+        parse_json_string(json_string).unwrap()
+    }
+
+    fn rfc_6901_document() -> JsonValue {
+        json(
+            r#"{
+                "foo": ["bar", "baz"],
+                "": 0,
+                "a/b": 1,
+                "c%d": 2,
+                "e^f": 3,
+                "g|h": 4,
+                "i\\j": 5,
+                "k\"l": 6,
+                " ": 7,
+                "m~n": 8
+            }"#,
+        )
+    }
+
+    #[test]
+    fn follows_the_rfc_examples() {
+        let document = rfc_6901_document();
+        let cases = [
+            ("/foo", json(r#"["bar", "baz"]"#)),
+            ("/foo/0", json(r#""bar""#)),
+            ("/", json("0")),
+            ("/a~1b", json("1")),
+            ("/c%d", json("2")),
+            ("/e^f", json("3")),
+            ("/g|h", json("4")),
+            ("/i\\j", json("5")),
+            ("/k\"l", json("6")),
+            ("/ ", json("7")),
+            ("/m~0n", json("8")),
+        ];
+
+        assert_eq!(Ok(&document), document.pointer(""));
+        for (pointer, expected) in cases {
+            assert_eq!(Ok(&expected), document.pointer(pointer), "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn tells_which_segment_failed() {
+        let document = json(r#"{"a": {"b~c": [10, {"d": true}]}}"#);
+        let cases = [
+            ("a", PointerErrorKind::NoLeadingSlash, 0, ""),
+            ("/a/b~2", PointerErrorKind::InvalidEscape, 1, "/a/b~2"),
+            ("/a/x~", PointerErrorKind::InvalidEscape, 1, "/a/x~"),
+            (
+                "/a/b/c",
+                PointerErrorKind::MissingKey(String::from("b")),
+                1,
+                "/a/b",
+            ),
+            (
+                "/a/b~0c/01",
+                PointerErrorKind::InvalidIndex(String::from("01")),
+                2,
+                "/a/b~0c/01",
+            ),
+            (
+                "/a/b~0c/+1",
+                PointerErrorKind::InvalidIndex(String::from("+1")),
+                2,
+                "/a/b~0c/+1",
+            ),
+            (
+                "/a/b~0c/2",
+                PointerErrorKind::IndexOutOfBounds { index: 2, len: 2 },
+                2,
+                "/a/b~0c/2",
+            ),
+            (
+                "/a/b~0c/-",
+                PointerErrorKind::IndexOutOfBounds { index: 2, len: 2 },
+                2,
+                "/a/b~0c/-",
+            ),
+            (
+                "/a/b~0c/0/x",
+                PointerErrorKind::NotAContainer,
+                3,
+                "/a/b~0c/0/x",
+            ),
+        ];
+
+        for (pointer, kind, segment, path) in cases {
+            let error = document.pointer(pointer).unwrap_err();
+            assert_eq!(
+                (&kind, segment, path),
+                (error.kind(), error.segment(), error.path())
+            );
+        }
+
+        assert_eq!(
+            "no member named \"b\" at \"/a/b\"",
+            document.pointer("/a/b/c").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn edits_values_by_pointer() {
+        let mut document = json(r#"{"a": {"list": [1, 2]}, "b": null}"#);
+
+        *document.pointer_mut("/a/list/0").unwrap() = json("10");
+        assert_eq!(Ok(None), document.pointer_insert("/a/list/1", json("15")));
+        assert_eq!(Ok(None), document.pointer_insert("/a/list/-", json("30")));
+        assert_eq!(Ok(None), document.pointer_insert("/a/new", json("true")));
+        assert_eq!(
+            Ok(Some(json("null"))),
+            document.pointer_insert("/b", json(r#""x""#))
+        );
+        assert_eq!(
+            Ok(json("2")),
+            document.pointer_replace("/a/list/2", json("20"))
+        );
+        assert_eq!(Ok(json("15")), document.pointer_remove("/a/list/1"));
+
+        assert_eq!(
+            json(r#"{"a": {"list": [10, 20, 30], "new": true}, "b": "x"}"#),
+            document
+        );
+    }
+
+    #[test]
+    fn rejects_edits_to_missing_places() {
+        let mut document = json(r#"{"a": [1]}"#);
+        let original = document.clone();
+
+        let errors = [
+            document.pointer_insert("/a/2", json("0")).unwrap_err(),
+            document.pointer_insert("/b/c", json("0")).unwrap_err(),
+            document.pointer_insert("/a/0/x", json("0")).unwrap_err(),
+            document.pointer_replace("/b", json("0")).unwrap_err(),
+            document.pointer_replace("/a/-", json("0")).unwrap_err(),
+            document.pointer_remove("/b").unwrap_err(),
+            document.pointer_remove("/a/1").unwrap_err(),
+            document.pointer_remove("").unwrap_err(),
+        ];
+        let kinds: Vec<_> = errors.iter().map(PointerError::kind).cloned().collect();
+
+        assert_eq!(
+            vec![
+                PointerErrorKind::IndexOutOfBounds { index: 2, len: 1 },
+                PointerErrorKind::MissingKey(String::from("b")),
+                PointerErrorKind::NotAContainer,
+                PointerErrorKind::MissingKey(String::from("b")),
+                PointerErrorKind::IndexOutOfBounds { index: 1, len: 1 },
+                PointerErrorKind::MissingKey(String::from("b")),
+                PointerErrorKind::IndexOutOfBounds { index: 1, len: 1 },
+                PointerErrorKind::RemoveRoot,
+            ],
+            kinds
+        );
+        assert_eq!(original, document);
+    }
+
+    #[test]
+    fn replaces_the_whole_value_at_the_empty_pointer() {
+        let mut document = json("[1]");
+
+        assert_eq!(
+            Ok(Some(json("[1]"))),
+            document.pointer_insert("", json("{}"))
+        );
+        assert_eq!(Ok(json("{}")), document.pointer_replace("", json("2")));
+        assert_eq!(json("2"), document);
+    }
+
+    #[test]
+    fn escapes_segments_back() {
+        let pointer = Pointer::parse("/a~1b/m~0n/~01").unwrap();
+
+        assert_eq!(vec!["a/b", "m~n", "~1"], pointer.segments);
+        assert_eq!("/a~1b/m~0n/~01", Pointer::format(&pointer.segments));
+    }
+}
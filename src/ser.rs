@@ -0,0 +1,467 @@
+use std::{
+    fmt::{self, Write},
+    io,
+};
+
+use crate::JsonValue;
+
+/// The whitespace used for one level of indentation by the pretty printer.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs(usize),
+}
+
+/// The line ending written by the pretty printer.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Newline {
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    fn as_str(self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Settings for `JsonValue::to_pretty_string` and `JsonValue::write_pretty`.
+///
+/// The default indents with two spaces, keeps the keys in the order of the map, ends lines
+/// with `\n` and puts every array element on its own line.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PrettyConfig {
+    pub indent: Indent,
+    /// Write object members sorted by key, which makes the output stable between runs.
+    pub sort_keys: bool,
+    pub newline: Newline,
+    /// Arrays holding only strings, numbers, booleans and nulls are kept on one line when
+    /// that line, `[1, 2, 3]`, is at most this many characters long.
+    pub short_array_width: Option<usize>,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            indent: Indent::Spaces(2),
+            sort_keys: false,
+            newline: Newline::Lf,
+            short_array_width: None,
+        }
+    }
+}
+
+impl JsonValue {
+    /// Formats the value as indented JSON text. The text has no trailing newline.
+    pub fn to_pretty_string(&self, config: &PrettyConfig) -> String {
+        let mut out = String::new();
+        // Writing to a String never fails
+        let _ = write_pretty(&mut out, self, config, 0);
+        out
+    }
+
+    /// Writes the value as indented JSON text to `writer`, see `to_pretty_string`.
+    pub fn write_pretty<W: io::Write>(&self, writer: W, config: &PrettyConfig) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            writer,
+            error: None,
+        };
+
+        match write_pretty(&mut adapter, self, config, 0) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+}
+
+// Lets the fmt::Write based writers target an io::Write, keeping the io::Error that
+// fmt::Error has no room for
+struct IoAdapter<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<W> {
+    fn write_str(&mut self, str: &str) -> fmt::Result {
+        self.writer.write_all(str.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+fn write_pretty<W: Write>(
+    out: &mut W,
+    value: &JsonValue,
+    config: &PrettyConfig,
+    depth: usize,
+) -> fmt::Result {
+    match value {
+        JsonValue::JsonObj(map) if !map.is_empty() => {
+            let mut members: Vec<_> = map.iter().collect();
+            if config.sort_keys {
+                members.sort_by(|a, b| a.0.cmp(b.0));
+            }
+
+            out.write_char('{')?;
+            for (i, (key, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_line_start(out, config, depth + 1)?;
+                write_string(out, key)?;
+                out.write_str(": ")?;
+                write_pretty(out, value, config, depth + 1)?;
+            }
+            write_line_start(out, config, depth)?;
+            out.write_char('}')
+        }
+        JsonValue::JsonArr(vector) if !vector.is_empty() => {
+            if let Some(line) = short_array_line(vector, config) {
+                return out.write_str(&line);
+            }
+
+            out.write_char('[')?;
+            for (i, value) in vector.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_line_start(out, config, depth + 1)?;
+                write_pretty(out, value, config, depth + 1)?;
+            }
+            write_line_start(out, config, depth)?;
+            out.write_char(']')
+        }
+        // Scalars and empty containers look the same as in compact output
+        value => write_compact(out, value),
+    }
+}
+
+fn write_line_start<W: Write>(out: &mut W, config: &PrettyConfig, depth: usize) -> fmt::Result {
+    out.write_str(config.newline.as_str())?;
+
+    let (ch, width) = match config.indent {
+        Indent::Spaces(width) => (' ', width),
+        Indent::Tabs(width) => ('\t', width),
+    };
+    for _ in 0..depth * width {
+        out.write_char(ch)?;
+    }
+
+    Ok(())
+}
+
+// The one line form of an array, if the config allows it and the array is short enough
+fn short_array_line(vector: &[JsonValue], config: &PrettyConfig) -> Option<String> {
+    let max_width = config.short_array_width?;
+    if vector
+        .iter()
+        .any(|value| matches!(value, JsonValue::JsonObj(_) | JsonValue::JsonArr(_)))
+    {
+        return None;
+    }
+
+    let mut line = String::from("[");
+    for (i, value) in vector.iter().enumerate() {
+        if i > 0 {
+            line.push_str(", ");
+        }
+        write_scalar(&mut line, value).ok()?;
+        if line.chars().count() >= max_width {
+            return None;
+        }
+    }
+    line.push(']');
+
+    (line.chars().count() <= max_width).then_some(line)
+}
+
+/// Writes `value` as compact JSON text, without any whitespace between tokens.
+pub(crate) fn write_compact<W: Write>(out: &mut W, value: &JsonValue) -> fmt::Result {
+    match value {
+        JsonValue::JsonObj(map) => {
+            out.write_char('{')?;
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_string(out, key)?;
+                out.write_char(':')?;
+                write_compact(out, value)?;
+            }
+            out.write_char('}')
+        }
+        JsonValue::JsonArr(vector) => {
+            out.write_char('[')?;
+            for (i, value) in vector.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_compact(out, value)?;
+            }
+            out.write_char(']')
+        }
+        scalar => write_scalar(out, scalar),
+    }
+}
+
+/// Writes a string, number, boolean or null. Containers are left to the caller.
+pub(crate) fn write_scalar<W: Write>(out: &mut W, value: &JsonValue) -> fmt::Result {
+    match value {
+        JsonValue::JsonString(str) => write_string(out, str),
+        JsonValue::JsonNum(num) => write_number(out, *num),
+        JsonValue::JsonBool(bin) => write!(out, "{}", bin),
+        JsonValue::JsonNull | JsonValue::JsonObj(_) | JsonValue::JsonArr(_) => {
+            out.write_str("null")
+        }
+    }
+}
+
+// JSON has no representation for NaN or the infinities, they are written as null like most
+// serializers do
+fn write_number<W: Write>(out: &mut W, num: f64) -> fmt::Result {
+    // Both formats give the shortest digits that read back to the same number and are valid
+    // JSON numbers, the exponent form keeps very large and very small numbers short
+    if num.is_finite() && num != 0.0 && !(1e-5..1e16).contains(&num.abs()) {
+        write!(out, "{:e}", num)
+    } else if num.is_finite() {
+        write!(out, "{}", num)
+    } else {
+        out.write_str("null")
+    }
+}
+
+pub(crate) fn write_string<W: Write>(out: &mut W, str: &str) -> fmt::Result {
+    out.write_char('"')?;
+
+    // Copy the runs of characters that need no escaping in one go
+    let mut run_start = 0;
+    for (pos, ch) in str.char_indices() {
+        let escaped = match ch {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{08}' => "\\b",
+            '\u{0C}' => "\\f",
+            c if c < '\u{20}' => "",
+            _ => continue,
+        };
+
+        out.write_str(&str[run_start..pos])?;
+        if escaped.is_empty() {
+            write!(out, "\\u{:04x}", ch as u32)?;
+        } else {
+            out.write_str(escaped)?;
+        }
+        run_start = pos + ch.len_utf8();
+    }
+    out.write_str(&str[run_start..])?;
+
+    out.write_char('"')
+}
+
+impl fmt::Display for JsonValue {
+    /// Formats the value as compact JSON text, so `to_string` gives a document that
+    /// `parse_json_string` reads back to an equal value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_compact(f, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json_string, JsonMap};
+    use proptest::prelude::*;
+
+    #[test]
+    fn writes_compact_json() {
+        let value = JsonValue::JsonArr(vec![
+            JsonValue::JsonNull,
+            JsonValue::JsonBool(true),
+            JsonValue::JsonNum(-1.5),
+            JsonValue::JsonNum(100.0),
+            JsonValue::JsonObj(JsonMap::from([(
+                String::from("a"),
+                JsonValue::JsonArr(vec![]),
+            )])),
+            JsonValue::JsonString(String::from("hi")),
+        ]);
+
+        assert_eq!(r#"[null,true,-1.5,100,{"a":[]},"hi"]"#, value.to_string());
+    }
+
+    #[test]
+    fn writes_large_and_small_numbers_with_exponent() {
+        assert_eq!("1e300", JsonValue::JsonNum(1e300).to_string());
+        assert_eq!("-2.5e-7", JsonValue::JsonNum(-2.5e-7).to_string());
+        assert_eq!("0.00001", JsonValue::JsonNum(1e-5).to_string());
+    }
+
+    #[test]
+    fn escapes_strings() {
+        let value = JsonValue::JsonString(String::from(
+            "\"q\" \\ / \n\r\t\u{08}\u{0C}\u{01}\u{1F} é 😀",
+        ));
+
+        assert_eq!(
+            r#""\"q\" \\ / \n\r\t\b\f\u0001\u001f é 😀""#,
+            value.to_string()
+        );
+    }
+
+    #[test]
+    fn writes_numbers_that_read_back_exactly() {
+        for num in [
+            0.0,
+            -0.0,
+            0.1,
+            1e300,
+            -2.5e-300,
+            5e-324,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+        ] {
+            let json_string = JsonValue::JsonNum(num).to_string();
+            assert_eq!(Ok(JsonValue::JsonNum(num)), parse_json_string(&json_string));
+        }
+    }
+
+    #[test]
+    fn writes_non_finite_numbers_as_null() {
+        assert_eq!("null", JsonValue::JsonNum(f64::NAN).to_string());
+        assert_eq!(
+            "[null]",
+            JsonValue::JsonArr(vec![JsonValue::JsonNum(f64::INFINITY)]).to_string()
+        );
+    }
+
+    fn arb_json_value() -> impl Strategy<Value = JsonValue> {
+        let leaf = prop_oneof![
+            Just(JsonValue::JsonNull),
+            any::<bool>().prop_map(JsonValue::JsonBool),
+            (prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO)
+                .prop_map(JsonValue::JsonNum),
+            any::<String>().prop_map(JsonValue::JsonString),
+        ];
+
+        leaf.prop_recursive(6, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(JsonValue::JsonArr),
+                prop::collection::vec((any::<String>(), inner), 0..8)
+                    .prop_map(|members| JsonValue::JsonObj(members.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn nested_value() -> JsonValue {
+        JsonValue::JsonObj(JsonMap::from([
+            (
+                String::from("b"),
+                JsonValue::JsonArr(vec![JsonValue::JsonNum(1.0), JsonValue::JsonNum(2.0)]),
+            ),
+            (String::from("a"), JsonValue::JsonObj(JsonMap::new())),
+            (
+                String::from("c"),
+                JsonValue::JsonObj(JsonMap::from([(String::from("d"), JsonValue::JsonNull)])),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn pretty_prints_with_sorted_keys() {
+        let config = PrettyConfig {
+            sort_keys: true,
+            ..PrettyConfig::default()
+        };
+
+        assert_eq!(
+            "{\n  \"a\": {},\n  \"b\": [\n    1,\n    2\n  ],\n  \"c\": {\n    \"d\": null\n  }\n}",
+            nested_value().to_pretty_string(&config)
+        );
+    }
+
+    #[test]
+    fn pretty_prints_with_tabs_and_crlf() {
+        let config = PrettyConfig {
+            indent: Indent::Tabs(1),
+            sort_keys: true,
+            newline: Newline::CrLf,
+            short_array_width: None,
+        };
+
+        assert_eq!(
+            "{\r\n\t\"a\": {},\r\n\t\"b\": [\r\n\t\t1,\r\n\t\t2\r\n\t],\r\n\t\"c\": {\r\n\t\t\"d\": null\r\n\t}\r\n}",
+            nested_value().to_pretty_string(&config)
+        );
+    }
+
+    #[test]
+    fn keeps_short_arrays_on_one_line() {
+        let config = PrettyConfig {
+            indent: Indent::Spaces(4),
+            short_array_width: Some(12),
+            ..PrettyConfig::default()
+        };
+        let short = JsonValue::JsonArr(vec![JsonValue::JsonNum(1.0), JsonValue::JsonNum(2.0)]);
+        let long = JsonValue::JsonArr(vec![JsonValue::from("abcdef"), JsonValue::from("gh")]);
+        let nested = JsonValue::JsonArr(vec![JsonValue::JsonArr(vec![])]);
+
+        assert_eq!("[1, 2]", short.to_pretty_string(&config));
+        assert_eq!(
+            "[\n    \"abcdef\",\n    \"gh\"\n]",
+            long.to_pretty_string(&config)
+        );
+        assert_eq!("[\n    []\n]", nested.to_pretty_string(&config));
+    }
+
+    #[test]
+    fn writes_pretty_output_to_io_writers() {
+        let config = PrettyConfig::default();
+        let value = nested_value();
+        let mut bytes = Vec::new();
+
+        value.write_pretty(&mut bytes, &config).unwrap();
+
+        assert_eq!(
+            value.to_pretty_string(&config),
+            String::from_utf8(bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn reports_io_errors_from_writers() {
+        let mut buf = [0u8; 4];
+        let err = nested_value()
+            .write_pretty(&mut buf[..], &PrettyConfig::default())
+            .unwrap_err();
+
+        assert_eq!(io::ErrorKind::WriteZero, err.kind());
+    }
+
+    proptest! {
+        #[test]
+        fn parses_back_pretty_printed_values(value in arb_json_value()) {
+            let config = PrettyConfig {
+                sort_keys: true,
+                short_array_width: Some(40),
+                ..PrettyConfig::default()
+            };
+            prop_assert_eq!(Ok(value.clone()), parse_json_string(&value.to_pretty_string(&config)));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn parses_back_serialized_values(value in arb_json_value()) {
+            prop_assert_eq!(Ok(value.clone()), parse_json_string(&value.to_string()));
+        }
+    }
+}
@@ -0,0 +1,1339 @@
+use std::{
+    io::{self, Read},
+    ops::Range,
+};
+
+use crate::{
+    parser::{duplicate_key_kind, insert_member, starts_number, starts_token, Parser, SeenKeys},
+    DuplicateKeys, JsonError, JsonErrorKind, JsonMap, JsonValue, ParserOptions, TextPosition,
+};
+
+// How much is asked of a reader at a time
+const READ_CHUNK_BYTES: usize = 8 * 1024;
+
+/// One step through a JSON document, as read by `JsonEventReader`. Keys come as their own
+/// event, right before the event that starts their value.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonEvent {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+}
+
+// The input handed to a streaming parser that it has not consumed yet. Only whole UTF-8
+// characters are kept as text, the bytes of a character split between two chunks wait in
+// `pending` for the rest of it.
+struct Input {
+    text: String,
+    // How much of `text` is consumed, it is dropped once that is at least half
+    start: usize,
+    pending: Vec<u8>,
+    // `pending` starts with bytes that are not UTF-8, nothing after them is read
+    invalid: bool,
+    finished: bool,
+    // Where `text[start..]` starts in the whole input
+    position: TextPosition,
+    // A token that ran into the end of the text isn't tried again before the text has grown
+    // to this length, which keeps a token split over many small chunks from being lexed once
+    // per chunk. It is tried as soon as a byte that can end it comes though
+    retry_len: usize,
+    // How much of the window has been looked through for such a byte
+    scanned: usize,
+}
+
+// The first byte after any whitespace
+enum Peeked {
+    Byte(u8),
+    End,
+    NeedMore,
+}
+
+impl Input {
+    fn new() -> Input {
+        Input {
+            text: String::new(),
+            start: 0,
+            pending: Vec::new(),
+            invalid: false,
+            finished: false,
+            position: TextPosition::START,
+            retry_len: 0,
+            scanned: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        if self.invalid || self.finished {
+            return;
+        }
+
+        if self.start > 0 && self.start >= self.text.len() / 2 {
+            self.text.drain(..self.start);
+            self.start = 0;
+        }
+
+        // At most the three bytes of a split character are left over from the previous chunk,
+        // so every byte is only checked about once
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(valid) => valid,
+            Err(error) => {
+                self.invalid = error.error_len().is_some();
+                std::str::from_utf8(&self.pending[..error.valid_up_to()]).unwrap_or_default()
+            }
+        };
+        let valid_len = valid.len();
+        self.text.push_str(valid);
+        self.pending.drain(..valid_len);
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        // A character cut short by the end of the input
+        if !self.pending.is_empty() {
+            self.invalid = true;
+        }
+    }
+
+    // Whether the text can't grow any more
+    fn is_complete(&self) -> bool {
+        self.finished || self.invalid
+    }
+
+    fn window(&self) -> &str {
+        &self.text[self.start..]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.position = self
+            .position
+            .advance(&self.text[self.start..self.start + len]);
+        self.start += len;
+    }
+
+    // Running into bytes that are not UTF-8 fails the document, wherever they are
+    fn invalid_utf8(&self) -> JsonError {
+        JsonError::at(
+            JsonErrorKind::InvalidUtf8,
+            self.position.advance(self.window()),
+        )
+    }
+
+    fn peek(&mut self) -> Result<Peeked, JsonError> {
+        let whitespace = self
+            .window()
+            .bytes()
+            .take_while(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+            .count();
+        self.consume(whitespace);
+
+        match self.window().bytes().next() {
+            Some(b) => Ok(Peeked::Byte(b)),
+            None if self.invalid => Err(self.invalid_utf8()),
+            None if self.finished => Ok(Peeked::End),
+            None => Ok(Peeked::NeedMore),
+        }
+    }
+
+    fn unexpected_character(&self) -> JsonError {
+        match self.window().chars().next() {
+            Some(ch) => JsonError::at(JsonErrorKind::UnexpectedCharacter(ch), self.position),
+            None => JsonError::at(JsonErrorKind::UnexpectedEnd, self.position),
+        }
+    }
+
+    // Lexes the token at the start of the text with one of the one-shot parser's functions,
+    // and consumes it. None means the token may go on past the end of the text, it is lexed
+    // again once more input has come.
+    //
+    // The lexing functions consume every byte they look at, so a token whose outcome could
+    // change with more input is one that left the parser at the end of the text. A string
+    // is done at its closing quote though, while a number or literal needs the byte after it.
+    fn lex<T>(
+        &mut self,
+        options: &ParserOptions,
+        ends_itself: bool,
+        lex: impl FnOnce(&mut Parser) -> Result<T, JsonError>,
+    ) -> Result<Option<(T, TextPosition, usize)>, JsonError> {
+        if !self.is_complete() && self.window().len() < self.retry_len && !self.may_end(ends_itself)
+        {
+            return Ok(None);
+        }
+        let window = self.window();
+
+        let mut parser = Parser::with_base(window, self.position, options.clone());
+        let result = lex(&mut parser);
+        let len = parser.pos();
+        let at_end = len == window.len();
+
+        match result {
+            Ok(value) if ends_itself || !at_end || self.is_complete() => {
+                let start = self.position;
+                self.consume(len);
+                self.retry_len = 0;
+                self.scanned = 0;
+                Ok(Some((value, start, start.offset + len)))
+            }
+            // More input can't make a string shorter
+            Err(error) if !at_end || *error.kind() == JsonErrorKind::StringTooLong => Err(error),
+            Err(_) if self.invalid => Err(self.invalid_utf8()),
+            Err(error) if self.finished => Err(error),
+            _ => {
+                let window_len = window.len();
+                self.retry_len = window_len * 2 + 1;
+                self.scanned = window_len;
+                Ok(None)
+            }
+        }
+    }
+
+    // Whether the text that came since the token at the start of the window was last lexed
+    // holds a byte that can end it: an unescaped quote for a string, and anything that can't
+    // be part of a number or literal for the others. Each byte is only looked at once, apart
+    // from the backslashes before a quote
+    fn may_end(&mut self, string: bool) -> bool {
+        let new = self.scanned.max(1)..self.window().len();
+        self.scanned = new.end;
+        let window = self.window().as_bytes();
+
+        new.into_iter().any(|i| match window[i] {
+            b'"' if string => {
+                let backslashes = window[..i]
+                    .iter()
+                    .rev()
+                    .take_while(|&&b| b == b'\\')
+                    .count();
+                backslashes % 2 == 0
+            }
+            _ if string => false,
+            b => !b.is_ascii_alphanumeric() && !matches!(b, b'+' | b'-' | b'.'),
+        })
+    }
+}
+
+// An array or object that is not closed yet
+enum Frame {
+    Array { start: TextPosition, len: usize },
+    Object { start: TextPosition, members: usize },
+}
+
+impl Frame {
+    fn start(&self) -> TextPosition {
+        match self {
+            Frame::Array { start, .. } | Frame::Object { start, .. } => *start,
+        }
+    }
+}
+
+// Where the parser is in the grammar, each state stands for a point in the one-shot parser
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum State {
+    Value { nested: bool },
+    ArrayFirst,
+    ArrayElement,
+    ArrayAfterValue,
+    ArrayAfterComma,
+    ObjectFirst,
+    ObjectMember,
+    ObjectKey,
+    ObjectColon,
+    ObjectAfterValue,
+    Trailing,
+    Done,
+}
+
+pub(crate) enum Step {
+    /// An event with the position it starts at and the offset it ends at.
+    Event(JsonEvent, TextPosition, usize),
+    NeedMore,
+    End,
+}
+
+/// Resumable parser over input that arrives a piece at a time.
+///
+/// It follows the one-shot recursive descent parser step by step, with the call stack turned
+/// into a stack of frames, so a document gives the same events and the same errors at the
+/// same positions wherever the input is split. Memory use is bounded by the nesting depth and
+/// the longest token.
+pub(crate) struct StreamParser {
+    input: Input,
+    options: ParserOptions,
+    frames: Vec<Frame>,
+    state: State,
+    fed: usize,
+    error: Option<JsonError>,
+}
+
+impl StreamParser {
+    pub(crate) fn new(options: ParserOptions) -> StreamParser {
+        StreamParser {
+            input: Input::new(),
+            options,
+            frames: Vec::new(),
+            state: State::Value { nested: false },
+            fed: 0,
+            error: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        self.fed += bytes.len();
+        match self.options.max_input_bytes {
+            Some(max) if self.fed > max => {
+                self.error.get_or_insert(JsonError::at(
+                    JsonErrorKind::InputTooLarge,
+                    TextPosition::START,
+                ));
+            }
+            _ => self.input.push(bytes),
+        }
+    }
+
+    /// Marks the end of the input.
+    pub(crate) fn finish(&mut self) {
+        self.input.finish();
+    }
+
+    /// Where the parser has got to in the input.
+    pub(crate) fn position(&self) -> TextPosition {
+        self.input.position
+    }
+
+    /// The next event, or what keeps it from being read. After an error, the same error is
+    /// returned again.
+    pub(crate) fn next_step(&mut self) -> Result<Step, JsonError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        self.step()
+            .inspect_err(|error| self.error = Some(error.clone()))
+    }
+
+    fn step(&mut self) -> Result<Step, JsonError> {
+        loop {
+            let peeked = match self.state {
+                // These states look at no input
+                State::ArrayElement => {
+                    if let Some(Frame::Array { start, len }) = self.frames.last_mut() {
+                        check_members(&self.options, *len, *start)?;
+                        *len += 1;
+                    }
+                    self.state = State::Value { nested: true };
+                    continue;
+                }
+                State::ObjectMember => {
+                    if let Some(Frame::Object { start, members }) = self.frames.last_mut() {
+                        check_members(&self.options, *members, *start)?;
+                        *members += 1;
+                    }
+                    self.state = State::ObjectKey;
+                    continue;
+                }
+                State::Done => return Ok(Step::End),
+                _ => match self.input.peek()? {
+                    Peeked::NeedMore => return Ok(Step::NeedMore),
+                    Peeked::Byte(b) => Some(b),
+                    Peeked::End => None,
+                },
+            };
+
+            match (self.state, peeked) {
+                (State::Value { nested }, peeked) => return self.value(nested, peeked),
+
+                (State::ArrayFirst, Some(b']')) | (State::ArrayAfterValue, Some(b']')) => {
+                    return Ok(self.close(JsonEvent::EndArray))
+                }
+                (State::ArrayFirst, Some(b'}')) => return Err(self.input.unexpected_character()),
+                (State::ArrayFirst, _) => self.state = State::ArrayElement,
+                (State::ArrayAfterValue, Some(b',')) => {
+                    self.input.consume(1);
+                    self.state = State::ArrayAfterComma;
+                }
+                (State::ArrayAfterValue, peeked) => return Err(self.missing_comma(peeked)),
+                (State::ArrayAfterComma, Some(b']' | b'}')) => {
+                    return Err(self.error_here(JsonErrorKind::TrailingComma))
+                }
+                (State::ArrayAfterComma, _) => self.state = State::ArrayElement,
+
+                (State::ObjectFirst, Some(b'}')) | (State::ObjectAfterValue, Some(b'}')) => {
+                    return Ok(self.close(JsonEvent::EndObject))
+                }
+                (State::ObjectFirst, Some(b']')) => return Err(self.input.unexpected_character()),
+                (State::ObjectFirst, _) => self.state = State::ObjectMember,
+                (State::ObjectKey, Some(b'"')) => {
+                    return match self
+                        .input
+                        .lex(&self.options, true, |parser| parser.parse_string())?
+                    {
+                        Some((key, start, end)) => {
+                            self.state = State::ObjectColon;
+                            Ok(Step::Event(JsonEvent::Key(key), start, end))
+                        }
+                        None => Ok(Step::NeedMore),
+                    }
+                }
+                // The opening brace has been handled, so this can only follow a comma
+                (State::ObjectKey, Some(b'}' | b']')) => {
+                    return Err(self.error_here(JsonErrorKind::TrailingComma))
+                }
+                (State::ObjectKey, Some(b)) if starts_token(b) || b == b',' || b == b':' => {
+                    return Err(self.error_here(JsonErrorKind::ExpectedKey))
+                }
+                (State::ObjectColon, Some(b':')) => {
+                    self.input.consume(1);
+                    self.state = State::Value { nested: true };
+                }
+                (State::ObjectColon, Some(b))
+                    if starts_token(b) || matches!(b, b',' | b'}' | b']') =>
+                {
+                    return Err(self.error_here(JsonErrorKind::MissingColon))
+                }
+                (State::ObjectKey | State::ObjectColon, None) => return Err(self.unclosed()),
+                (State::ObjectKey | State::ObjectColon, Some(_)) => {
+                    return Err(self.input.unexpected_character())
+                }
+                (State::ObjectAfterValue, Some(b',')) => {
+                    self.input.consume(1);
+                    self.state = State::ObjectMember;
+                }
+                (State::ObjectAfterValue, peeked) => return Err(self.missing_comma(peeked)),
+
+                (State::Trailing, None) => {
+                    self.state = State::Done;
+                    return Ok(Step::End);
+                }
+                (State::Trailing, Some(_)) => {
+                    return Err(self.error_here(JsonErrorKind::TrailingData))
+                }
+
+                (State::ArrayElement | State::ObjectMember | State::Done, _) => (),
+            }
+        }
+    }
+
+    // A value starts at `peeked`, `nested` tells whether it is inside a container
+    fn value(&mut self, nested: bool, peeked: Option<u8>) -> Result<Step, JsonError> {
+        let options = &self.options;
+        let lexed = match peeked {
+            None if nested => return Err(self.unclosed()),
+            None => return Err(self.error_here(JsonErrorKind::UnexpectedEnd)),
+            Some(b @ (b'{' | b'[')) => {
+                if self.frames.len() >= self.options.max_depth {
+                    return Err(self.error_here(JsonErrorKind::TooDeep));
+                }
+
+                let start = self.input.position;
+                self.input.consume(1);
+                let event = if b == b'{' {
+                    self.frames.push(Frame::Object { start, members: 0 });
+                    self.state = State::ObjectFirst;
+                    JsonEvent::StartObject
+                } else {
+                    self.frames.push(Frame::Array { start, len: 0 });
+                    self.state = State::ArrayFirst;
+                    JsonEvent::StartArray
+                };
+                return Ok(Step::Event(event, start, start.offset + 1));
+            }
+            Some(b'"') => self
+                .input
+                .lex(options, true, |parser| parser.parse_string())?
+                .map(|(str, start, end)| (JsonEvent::String(str), start, end)),
+            Some(b) if starts_number(b) => self
+                .input
+                .lex(options, false, |parser| parser.parse_number())?
+                .map(|(num, start, end)| (JsonEvent::Number(num), start, end)),
+            Some(b) if b.is_ascii_alphabetic() => self
+                .input
+                .lex(options, false, |parser| parser.parse_literal())?
+                .map(|(literal, start, end)| {
+                    let event = literal.map_or(JsonEvent::Null, JsonEvent::Bool);
+                    (event, start, end)
+                }),
+            Some(b']' | b'}') if !nested => return Err(self.input.unexpected_character()),
+            Some(b']' | b'}' | b',' | b':') => {
+                return Err(self.error_here(JsonErrorKind::ExpectedValue))
+            }
+            Some(_) => return Err(self.input.unexpected_character()),
+        };
+
+        match lexed {
+            Some((event, start, end)) => {
+                self.state = self.after_value();
+                Ok(Step::Event(event, start, end))
+            }
+            None => Ok(Step::NeedMore),
+        }
+    }
+
+    fn close(&mut self, event: JsonEvent) -> Step {
+        let start = self.input.position;
+        self.input.consume(1);
+        self.frames.pop();
+        self.state = self.after_value();
+
+        Step::Event(event, start, start.offset + 1)
+    }
+
+    fn after_value(&self) -> State {
+        match self.frames.last() {
+            None => State::Trailing,
+            Some(Frame::Array { .. }) => State::ArrayAfterValue,
+            Some(Frame::Object { .. }) => State::ObjectAfterValue,
+        }
+    }
+
+    // Error for whatever stands where a comma or the end of the container was expected
+    fn missing_comma(&self, peeked: Option<u8>) -> JsonError {
+        match peeked {
+            None => self.unclosed(),
+            Some(b) if starts_token(b) => self.error_here(JsonErrorKind::MissingComma),
+            Some(_) => self.input.unexpected_character(),
+        }
+    }
+
+    fn unclosed(&self) -> JsonError {
+        match self.frames.last() {
+            Some(frame) => JsonError::at(JsonErrorKind::UnclosedContainer, frame.start()),
+            None => self.error_here(JsonErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn error_here(&self, kind: JsonErrorKind) -> JsonError {
+        JsonError::at(kind, self.input.position)
+    }
+}
+
+fn check_members(
+    options: &ParserOptions,
+    members: usize,
+    start: TextPosition,
+) -> Result<(), JsonError> {
+    match options.max_members {
+        Some(max) if members >= max => Err(JsonError::at(JsonErrorKind::TooManyMembers, start)),
+        _ => Ok(()),
+    }
+}
+
+// An array or object whose members are still being added
+enum Partial {
+    Array(Vec<JsonValue>),
+    Object {
+        map: JsonMap,
+        keys: SeenKeys<TextPosition>,
+        key: Option<(String, TextPosition)>,
+    },
+}
+
+/// Builds values out of events, applying the duplicate key policy like the one-shot parser.
+pub(crate) struct ValueBuilder {
+    duplicate_keys: DuplicateKeys,
+    open: Vec<Partial>,
+}
+
+impl ValueBuilder {
+    pub(crate) fn new(duplicate_keys: DuplicateKeys) -> ValueBuilder {
+        ValueBuilder {
+            duplicate_keys,
+            open: Vec::new(),
+        }
+    }
+
+    /// Adds an event starting at `start`, and returns the top-level value once it is complete.
+    pub(crate) fn push(
+        &mut self,
+        event: JsonEvent,
+        start: TextPosition,
+    ) -> Result<Option<JsonValue>, JsonError> {
+        let value = match event {
+            JsonEvent::StartObject => {
+                self.open.push(Partial::Object {
+                    map: JsonMap::new(),
+                    keys: SeenKeys::default(),
+                    key: None,
+                });
+                return Ok(None);
+            }
+            JsonEvent::StartArray => {
+                self.open.push(Partial::Array(Vec::new()));
+                return Ok(None);
+            }
+            JsonEvent::Key(name) => {
+                if let Some(Partial::Object { key, .. }) = self.open.last_mut() {
+                    *key = Some((name, start));
+                }
+                return Ok(None);
+            }
+            JsonEvent::EndObject | JsonEvent::EndArray => match self.open.pop() {
+                Some(Partial::Array(vector)) => JsonValue::JsonArr(vector),
+                Some(Partial::Object { map, .. }) => JsonValue::JsonObj(map),
+                None => return Ok(None),
+            },
+            JsonEvent::String(str) => JsonValue::JsonString(str),
+            JsonEvent::Number(num) => JsonValue::JsonNum(num),
+            JsonEvent::Bool(bin) => JsonValue::JsonBool(bin),
+            JsonEvent::Null => JsonValue::JsonNull,
+        };
+
+        match self.open.last_mut() {
+            None => return Ok(Some(value)),
+            Some(Partial::Array(vector)) => vector.push(value),
+            Some(Partial::Object { map, keys, key }) => {
+                if let Some((key, key_pos)) = key.take() {
+                    insert_member(self.duplicate_keys, map, keys, key, key_pos, value).map_err(
+                        |(key, first)| JsonError::at(duplicate_key_kind(key, first), key_pos),
+                    )?;
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Pull parser over a reader, for documents too large to hold as a `JsonValue`.
+///
+/// Iterates over the events of one document along with the byte range of the input each one
+/// covers, from the opening quote of a string to its closing one for instance. The input is
+/// read in chunks as the events are asked for, and memory use is bounded by the nesting depth
+/// and the longest string or number, whatever the size of the document.
+///
+/// The whole grammar is checked as the events come, so an invalid document gives the events
+/// of its valid start and then the same error as `parse_json_reader`. The iteration ends after
+/// the error, or after the end of the document once the trailing whitespace has been read.
+///
+/// `ParserOptions` limits apply as they do for the other parsers. `duplicate_keys` doesn't,
+/// since checking keys would mean remembering all of them, every key is passed on as it is.
+///
+/// ```
+/// use json_reader::{JsonEvent, JsonEventReader};
+///
+/// let input = r#"{"id": 7, "tags": ["a"]}"#;
+/// let keys: Vec<String> = JsonEventReader::new(input.as_bytes())
+///     .filter_map(|event| match event {
+///         Ok((JsonEvent::Key(key), _span)) => Some(key),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(vec!["id", "tags"], keys);
+/// ```
+pub struct JsonEventReader<R> {
+    reader: R,
+    parser: StreamParser,
+    chunk: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> JsonEventReader<R> {
+    pub fn new(reader: R) -> JsonEventReader<R> {
+        JsonEventReader::with_options(reader, &ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: &ParserOptions) -> JsonEventReader<R> {
+        JsonEventReader {
+            reader,
+            parser: StreamParser::new(options.clone()),
+            chunk: vec![0; READ_CHUNK_BYTES],
+            done: false,
+        }
+    }
+
+    /// Gives back the reader. It may have been read past the last event returned.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // The next event with the position it starts at and the offset it ends at
+    fn next_event(&mut self) -> Result<Option<(JsonEvent, TextPosition, usize)>, JsonError> {
+        loop {
+            match self.parser.next_step()? {
+                Step::Event(event, start, end) => return Ok(Some((event, start, end))),
+                Step::NeedMore => match self.reader.read(&mut self.chunk) {
+                    Ok(0) => self.parser.finish(),
+                    Ok(len) => self.parser.push(&self.chunk[..len]),
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+                    Err(error) => {
+                        return Err(JsonError::at(
+                            JsonErrorKind::Io(error.kind()),
+                            self.parser.position(),
+                        ))
+                    }
+                },
+                Step::End => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonEventReader<R> {
+    type Item = Result<(JsonEvent, Range<usize>), JsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_event() {
+            Ok(Some((event, start, end))) => Some(Ok((event, start.offset..end))),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl<R: Read> std::iter::FusedIterator for JsonEventReader<R> {}
+
+/// What a `JsonPushParser` has to give after the input fed so far.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Progress<T> {
+    Ready(T),
+    /// The input so far has been used up, `feed` more or `finish` it.
+    NeedMore,
+    /// The document is over.
+    End,
+}
+
+/// Push parser for input that arrives in pieces, from an event loop for instance, where
+/// blocking on an `io::Read` isn't an option.
+///
+/// Input is handed over with `feed`, in slices split anywhere, even inside a string, an
+/// escape, a UTF-8 character or a number. `next_event` or `next_value` then give what can be
+/// read so far, or `Progress::NeedMore` once the input is used up, and `finish` tells the
+/// parser there is no more. Whatever the split, the events, the value and any error are the
+/// same as for the whole input parsed at once, with the one difference `JsonEventReader` has
+/// about `max_input_bytes`.
+///
+/// A parser reads one document. Use either `next_event` or `next_value` on it, not both, and
+/// once it has returned an error it keeps returning that error.
+///
+/// ```
+/// use json_reader::{JsonPushParser, JsonValue, Progress};
+///
+/// let mut parser = JsonPushParser::new();
+/// // Split inside the two bytes of "é"
+/// parser.feed(b"{\"name\": \"caf\xc3");
+/// assert_eq!(Ok(Progress::NeedMore), parser.next_value());
+///
+/// parser.feed(b"\xa9\"}");
+/// parser.finish();
+/// let value = match parser.next_value() {
+///     Ok(Progress::Ready(value)) => value,
+///     other => panic!("{:?}", other),
+/// };
+/// let name = value.as_map().unwrap()["name"].clone();
+/// assert_eq!(JsonValue::JsonString(String::from("café")), name);
+/// ```
+pub struct JsonPushParser {
+    parser: StreamParser,
+    builder: ValueBuilder,
+    value: Option<JsonValue>,
+}
+
+impl JsonPushParser {
+    pub fn new() -> JsonPushParser {
+        JsonPushParser::with_options(&ParserOptions::default())
+    }
+
+    pub fn with_options(options: &ParserOptions) -> JsonPushParser {
+        JsonPushParser {
+            parser: StreamParser::new(options.clone()),
+            builder: ValueBuilder::new(options.duplicate_keys),
+            value: None,
+        }
+    }
+
+    /// Adds the next piece of input. Input fed after `finish` is ignored.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.parser.push(bytes);
+    }
+
+    /// Marks the end of the input.
+    pub fn finish(&mut self) {
+        self.parser.finish();
+    }
+
+    /// The next event along with the byte range of the input it covers.
+    pub fn next_event(&mut self) -> Result<Progress<(JsonEvent, Range<usize>)>, JsonError> {
+        Ok(match self.parser.next_step()? {
+            Step::Event(event, start, end) => Progress::Ready((event, start.offset..end)),
+            Step::NeedMore => Progress::NeedMore,
+            Step::End => Progress::End,
+        })
+    }
+
+    /// The document. It is only given once the input is finished, since anything but
+    /// whitespace after it makes the document invalid.
+    pub fn next_value(&mut self) -> Result<Progress<JsonValue>, JsonError> {
+        loop {
+            match self.parser.next_step()? {
+                Step::Event(event, start, _end) => {
+                    if let Some(value) = self.builder.push(event, start)? {
+                        self.value = Some(value);
+                    }
+                }
+                Step::NeedMore => return Ok(Progress::NeedMore),
+                Step::End => return Ok(self.value.take().map_or(Progress::End, Progress::Ready)),
+            }
+        }
+    }
+}
+
+impl Default for JsonPushParser {
+    fn default() -> Self {
+        JsonPushParser::new()
+    }
+}
+
+/// Parses a document from a reader, a chunk at a time, so the text is never all in memory.
+pub(crate) fn parse_reader<R: Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<JsonValue, JsonError> {
+    let mut events = JsonEventReader::with_options(reader, options);
+    let mut builder = ValueBuilder::new(options.duplicate_keys);
+    let mut document = None;
+
+    while let Some((event, start, _end)) = events.next_event()? {
+        if let Some(value) = builder.push(event, start)? {
+            document = Some(value);
+        }
+    }
+
+    // The parser only ends after a whole value
+    document.ok_or_else(|| JsonError::at(JsonErrorKind::UnexpectedEnd, events.parser.position()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parse_json_slice, parse_json_slice_with, parse_json_string, parse_json_string_with,
+    };
+    use proptest::prelude::*;
+
+    // Hands out the input a few bytes per read, split wherever the sizes fall
+    struct ChunkedReader<'a> {
+        bytes: &'a [u8],
+        sizes: Vec<usize>,
+        reads: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let size = self.sizes[self.reads % self.sizes.len()].max(1);
+            self.reads += 1;
+
+            let len = size.min(buf.len()).min(self.bytes.len());
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            Ok(len)
+        }
+    }
+
+    fn parse_chunked(
+        bytes: &[u8],
+        sizes: Vec<usize>,
+        options: &ParserOptions,
+    ) -> Result<JsonValue, JsonError> {
+        let reader = ChunkedReader {
+            bytes,
+            sizes,
+            reads: 0,
+        };
+        parse_reader(reader, options)
+    }
+
+    fn strict_options() -> ParserOptions {
+        ParserOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            max_depth: 3,
+            max_string_bytes: Some(6),
+            max_members: Some(3),
+            max_input_bytes: None,
+        }
+    }
+
+    fn assert_same_as_one_shot(json_string: &str, sizes: Vec<usize>) {
+        for options in [ParserOptions::default(), strict_options()] {
+            assert_eq!(
+                parse_json_string_with(json_string, &options),
+                parse_chunked(json_string.as_bytes(), sizes.clone(), &options),
+                "{:?} read in chunks of {:?}",
+                json_string,
+                sizes
+            );
+        }
+    }
+
+    #[test]
+    fn reads_documents_split_anywhere() {
+        let documents = [
+            r#"{"a": [1, 2.5e3, -0.1], "b": {"c": null}, "d": "x\u00e9\ud83d\ude00\n"}"#,
+            "  [true, false, null, \"caf\u{e9}\", \"\u{1f600}\"]  \n",
+            "123",
+            "-0.5e-7 ",
+            r#""\"\\\/\b\f\n\r\t""#,
+            "[[[]], {}, [{}]]",
+            "{\"a\":1,\n \"a\":2}",
+            "[1,2,3,4]",
+            r#""a long string""#,
+            "[[[[1]]]]",
+        ];
+
+        for document in documents {
+            for size in 1..8 {
+                assert_same_as_one_shot(document, vec![size]);
+            }
+            assert_same_as_one_shot(document, vec![READ_CHUNK_BYTES]);
+        }
+    }
+
+    #[test]
+    fn reports_errors_like_the_one_shot_parser() {
+        let documents = [
+            "",
+            "   ",
+            "[1, 2",
+            "[1 2]",
+            "[1,]",
+            "{\"a\" 1}",
+            "{\"a\":1,}",
+            "{,}",
+            "[\"abc",
+            "\"\\x\"",
+            "\"\\ud800\"",
+            "[tru]",
+            "[tru",
+            "01",
+            "1.",
+            "[1] x",
+            "{\"a\":\n  1 \"b\"}",
+            "[}",
+            "]",
+            "\"a\u{1}\"",
+            "[\"\u{e9}\u{e9}\u{e9}\u{e9}\"]",
+            "\u{feff}{}",
+        ];
+
+        for document in documents {
+            for size in 1..5 {
+                assert_same_as_one_shot(document, vec![size]);
+            }
+        }
+    }
+
+    #[test]
+    fn reports_invalid_utf8_at_the_first_bad_byte() {
+        let cases: [(&[u8], usize); 5] = [
+            (b"[\"a\xff\"]", 3),
+            (b"[1, 2]\n\xc3", 7),
+            (b"\"\xe2\x82", 1),
+            (b"nul\xff", 3),
+            (b"[1\xff]", 2),
+        ];
+
+        for (bytes, offset) in cases {
+            let error = parse_json_slice(bytes).unwrap_err();
+            assert_eq!(&JsonErrorKind::InvalidUtf8, error.kind(), "{:?}", bytes);
+            assert_eq!(offset, error.offset(), "{:?}", bytes);
+
+            for size in 1..4 {
+                assert_eq!(
+                    Err(error.clone()),
+                    parse_chunked(bytes, vec![size], &ParserOptions::default())
+                );
+            }
+        }
+
+        // Bytes after an error the parser has already found don't matter
+        let error = parse_json_slice(b"[1 2 \xff]").unwrap_err();
+        assert_eq!(&JsonErrorKind::MissingComma, error.kind());
+    }
+
+    #[test]
+    fn gives_events_without_reading_past_them() {
+        // Like a socket, each read gives what has arrived so far, and one more blocks
+        struct Pieces(Vec<&'static [u8]>);
+
+        impl Read for Pieces {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                assert!(!self.0.is_empty(), "read past the input that has arrived");
+                let piece = self.0.remove(0);
+                buf[..piece.len()].copy_from_slice(piece);
+                Ok(piece.len())
+            }
+        }
+
+        let reader = Pieces(vec![b"[\"abcdefgh", b"ijklmnop", b"\"", b", 12", b"3 "]);
+        let events: Vec<_> = JsonEventReader::new(reader)
+            .take(3)
+            .map(|event| event.unwrap().0)
+            .collect();
+        assert_eq!(
+            vec![
+                JsonEvent::StartArray,
+                JsonEvent::String(String::from("abcdefghijklmnop")),
+                JsonEvent::Number(123.0)
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn stops_on_a_failed_read() {
+        struct FailingReader(usize);
+
+        impl Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0 += 1;
+                match self.0 {
+                    1 => {
+                        buf[..3].copy_from_slice(b"[1,");
+                        Ok(3)
+                    }
+                    2 => Err(io::Error::from(io::ErrorKind::Interrupted)),
+                    3 => {
+                        buf[..2].copy_from_slice(b"\n2");
+                        Ok(2)
+                    }
+                    _ => Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+                }
+            }
+        }
+
+        let error = parse_reader(FailingReader(0), &ParserOptions::default()).unwrap_err();
+        assert_eq!(
+            &JsonErrorKind::Io(io::ErrorKind::ConnectionReset),
+            error.kind()
+        );
+        assert_eq!((4, 2, 1), (error.offset(), error.line(), error.column()));
+    }
+
+    #[test]
+    fn checks_the_input_size_as_it_comes() {
+        let options = ParserOptions {
+            max_input_bytes: Some(8),
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            parse_json_string("[1, 2]"),
+            parse_chunked(b"[1, 2]", vec![2], &options)
+        );
+        assert_eq!(
+            &JsonErrorKind::InputTooLarge,
+            parse_chunked(b"[1, 2, 3, 4]", vec![2], &options)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    fn events(json_string: &str) -> Vec<Result<(JsonEvent, Range<usize>), JsonError>> {
+        JsonEventReader::new(json_string.as_bytes()).collect()
+    }
+
+    #[test]
+    fn reads_events_with_their_spans() {
+        let json_string = "{\"a\": [1, true, null],\n \"b\": \"\u{e9}\\n\", \"c\": {}}";
+
+        let expected = vec![
+            (JsonEvent::StartObject, 0..1),
+            (JsonEvent::Key(String::from("a")), 1..4),
+            (JsonEvent::StartArray, 6..7),
+            (JsonEvent::Number(1.0), 7..8),
+            (JsonEvent::Bool(true), 10..14),
+            (JsonEvent::Null, 16..20),
+            (JsonEvent::EndArray, 20..21),
+            (JsonEvent::Key(String::from("b")), 24..27),
+            (JsonEvent::String(String::from("\u{e9}\n")), 29..35),
+            (JsonEvent::Key(String::from("c")), 37..40),
+            (JsonEvent::StartObject, 42..43),
+            (JsonEvent::EndObject, 43..44),
+            (JsonEvent::EndObject, 44..45),
+        ];
+        let events: Vec<_> = events(json_string)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(expected, events);
+        for (event, span) in events {
+            if let JsonEvent::Number(_) | JsonEvent::Bool(_) | JsonEvent::Null = event {
+                assert!(crate::parse_json_string(&json_string[span]).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn ends_with_the_first_error() {
+        let mut reader = JsonEventReader::new("[1, {\"a\" 2}]".as_bytes());
+
+        assert_eq!(Some(Ok((JsonEvent::StartArray, 0..1))), reader.next());
+        assert_eq!(Some(Ok((JsonEvent::Number(1.0), 1..2))), reader.next());
+        assert_eq!(Some(Ok((JsonEvent::StartObject, 4..5))), reader.next());
+        assert_eq!(
+            Some(Ok((JsonEvent::Key(String::from("a")), 5..8))),
+            reader.next()
+        );
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(&JsonErrorKind::MissingColon, error.kind());
+        assert_eq!(None, reader.next());
+
+        // Only one document is read, anything after it is an error
+        assert_eq!(
+            Some(&JsonErrorKind::TrailingData),
+            events("1 2")
+                .last()
+                .unwrap()
+                .as_ref()
+                .err()
+                .map(JsonError::kind)
+        );
+        assert!(events("").pop().unwrap().is_err());
+    }
+
+    // A reader of a long array, written as it is read
+    struct LongArray {
+        elements: usize,
+        started: bool,
+    }
+
+    impl Read for LongArray {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut len = 0;
+            if !self.started {
+                self.started = true;
+                buf[0] = b'[';
+                len = 1;
+            }
+
+            let element = b"{\"k\": [\"value\", 12.5]},";
+            while len + element.len() <= buf.len() && self.elements > 0 {
+                self.elements -= 1;
+                buf[len..len + element.len()].copy_from_slice(element);
+                len += element.len();
+                if self.elements == 0 {
+                    buf[len - 1] = b']';
+                }
+            }
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn reads_large_documents_in_bounded_memory() {
+        let mut events = JsonEventReader::new(LongArray {
+            elements: 200_000,
+            started: false,
+        });
+
+        let mut count = 0;
+        while let Some(event) = events.next() {
+            event.unwrap();
+            count += 1;
+            assert!(events.parser.input.text.capacity() <= 4 * READ_CHUNK_BYTES);
+        }
+        assert_eq!(2 + 200_000 * 7, count);
+    }
+
+    // Feeds the pieces one at a time and reads the value as soon as the parser can tell
+    fn push_parse(pieces: &[&[u8]], options: &ParserOptions) -> Result<JsonValue, JsonError> {
+        let mut parser = JsonPushParser::with_options(options);
+
+        for piece in pieces {
+            parser.feed(piece);
+            match parser.next_value()? {
+                Progress::NeedMore => (),
+                other => panic!("value before the input is finished: {:?}", other),
+            }
+        }
+        parser.finish();
+
+        match parser.next_value()? {
+            Progress::Ready(value) => {
+                assert_eq!(Ok(Progress::End), parser.next_value());
+                Ok(value)
+            }
+            other => panic!("no value at the end of the input: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pushes_input_split_at_every_byte() {
+        let documents = [
+            "{\"caf\u{e9}\": \"\\u00e9\\ud83d\\ude00\\\"\", \"\u{1f600}\": [-12.5e+3, 1e-2]}",
+            "12345",
+            "[true, false, null]",
+            "[1, 2",
+            "\"abc\\q\"",
+            "{\"a\": 1, \"a\": 2}",
+            "[1] 2",
+        ];
+
+        for document in documents {
+            let bytes = document.as_bytes();
+            for options in [ParserOptions::default(), strict_options()] {
+                let expected = parse_json_slice_with(bytes, &options);
+
+                for split in 0..=bytes.len() {
+                    let (first, second) = bytes.split_at(split);
+                    assert_eq!(
+                        expected,
+                        push_parse(&[first, second], &options),
+                        "{:?} split at {}",
+                        document,
+                        split
+                    );
+                }
+                let pieces: Vec<&[u8]> = bytes.chunks(1).collect();
+                assert_eq!(expected, push_parse(&pieces, &options));
+            }
+        }
+    }
+
+    #[test]
+    fn pushes_events_like_the_reader() {
+        let document = "{\"a\": [1, \"\u{e9}\"], \"b\": {\"c\": null}} x";
+        let expected = events(document);
+
+        let mut parser = JsonPushParser::new();
+        let mut pushed = Vec::new();
+        let mut pieces = document.as_bytes().chunks(3);
+        loop {
+            match parser.next_event() {
+                Ok(Progress::Ready(event)) => pushed.push(Ok(event)),
+                Ok(Progress::NeedMore) => match pieces.next() {
+                    Some(piece) => parser.feed(piece),
+                    None => parser.finish(),
+                },
+                Ok(Progress::End) => break,
+                Err(error) => {
+                    // The error stays
+                    assert_eq!(Err(error.clone()), parser.next_event());
+                    pushed.push(Err(error));
+                    break;
+                }
+            }
+        }
+
+        assert_eq!(expected, pushed);
+    }
+
+    #[test]
+    fn gives_values_as_soon_as_they_end() {
+        let mut parser = JsonPushParser::new();
+        let message = "hello world, this is a longer message";
+
+        parser.feed(format!("{{\"msg\": \"{}", message).as_bytes());
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Progress::Ready((JsonEvent::StartObject, _)))
+        ));
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Progress::Ready((JsonEvent::Key(_), _)))
+        ));
+        assert_eq!(Ok(Progress::NeedMore), parser.next_event());
+        parser.feed(b"\"}\n");
+        assert_eq!(
+            Ok(Progress::Ready((
+                JsonEvent::String(String::from(message)),
+                8..47
+            ))),
+            parser.next_event()
+        );
+        assert_eq!(
+            Ok(Progress::Ready((JsonEvent::EndObject, 47..48))),
+            parser.next_event()
+        );
+
+        // An escaped quote doesn't end a string
+        let mut parser = JsonPushParser::new();
+        let mut events = Vec::new();
+        for piece in ["[\"abcdefgh", "\\\"", "\"", ",", " ", "1", ","] {
+            parser.feed(piece.as_bytes());
+            while let Ok(Progress::Ready((event, _))) = parser.next_event() {
+                events.push(event);
+            }
+        }
+        assert_eq!(
+            vec![
+                JsonEvent::StartArray,
+                JsonEvent::String(String::from("abcdefgh\"")),
+                JsonEvent::Number(1.0)
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn waits_for_the_end_of_a_number() {
+        let mut parser = JsonPushParser::new();
+
+        parser.feed(b"[12");
+        assert_eq!(
+            Ok(Progress::Ready((JsonEvent::StartArray, 0..1))),
+            parser.next_event()
+        );
+        assert_eq!(Ok(Progress::NeedMore), parser.next_event());
+        parser.feed(b"34");
+        assert_eq!(Ok(Progress::NeedMore), parser.next_event());
+        parser.feed(b"]");
+        assert_eq!(
+            Ok(Progress::Ready((JsonEvent::Number(1234.0), 1..5))),
+            parser.next_event()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn reads_json_like_input_like_the_one_shot_parser(
+            json_string in r#"[\[\]{}:,"\\ u0-9a-fE.+\-éntrl\n\x00]{0,40}"#,
+            sizes in prop::collection::vec(1..6usize, 1..4),
+        ) {
+            assert_same_as_one_shot(&json_string, sizes);
+        }
+
+        #[test]
+        fn reads_bytes_like_the_slice_parser(
+            bytes in prop::collection::vec(
+                prop::sample::select(b"[]{}:,\"\\ 0123e.-trunl\xc3\xa9\xff".to_vec()),
+                0..40,
+            ),
+            sizes in prop::collection::vec(1..6usize, 1..4),
+        ) {
+            prop_assert_eq!(
+                parse_json_slice(&bytes),
+                parse_chunked(&bytes, sizes.clone(), &ParserOptions::default())
+            );
+
+            let mut pieces = Vec::new();
+            let mut rest = bytes.as_slice();
+            for size in sizes.iter().cycle() {
+                if rest.is_empty() {
+                    break;
+                }
+                let (piece, after) = rest.split_at((*size).min(rest.len()));
+                pieces.push(piece);
+                rest = after;
+            }
+            prop_assert_eq!(
+                parse_json_slice(&bytes),
+                push_parse(&pieces, &ParserOptions::default())
+            );
+        }
+    }
+}
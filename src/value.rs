@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::{InvalidJsonUnwrap, JsonMap, Token};
+
+/// A parsed JSON value.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonValue {
+    JsonString(String),
+    JsonNum(f64),
+    JsonBool(bool),
+    JsonNull,
+    JsonObj(JsonMap),
+    JsonArr(Vec<JsonValue>),
+}
+
+impl JsonValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::JsonNull)
+    }
+
+    pub fn as_str(&self) -> Result<&str, InvalidJsonUnwrap> {
+        match self {
+            JsonValue::JsonString(str) => Ok(str as &str),
+            _ => Err(InvalidJsonUnwrap),
+        }
+    }
+
+    pub fn as_f64(&self) -> Result<f64, InvalidJsonUnwrap> {
+        match self {
+            JsonValue::JsonNum(num) => Ok(*num),
+            _ => Err(InvalidJsonUnwrap),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, InvalidJsonUnwrap> {
+        match self {
+            JsonValue::JsonBool(bin) => Ok(*bin),
+            _ => Err(InvalidJsonUnwrap),
+        }
+    }
+
+    pub fn as_null(&self) -> Result<(), InvalidJsonUnwrap> {
+        match self {
+            JsonValue::JsonNull => Ok(()),
+            _ => Err(InvalidJsonUnwrap),
+        }
+    }
+
+    pub fn as_map(&self) -> Result<JsonMap, InvalidJsonUnwrap> {
+        match self {
+            JsonValue::JsonObj(map) => Ok(map.clone()),
+            _ => Err(InvalidJsonUnwrap),
+        }
+    }
+
+    pub fn as_vec(&self) -> Result<Vec<JsonValue>, InvalidJsonUnwrap> {
+        match self {
+            JsonValue::JsonArr(vector) => Ok(vector.clone()),
+            _ => Err(InvalidJsonUnwrap),
+        }
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self {
+        JsonValue::JsonString(value)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        JsonValue::JsonString(String::from(value))
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> Self {
+        JsonValue::JsonNum(value)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> Self {
+        JsonValue::JsonBool(value)
+    }
+}
+
+impl From<Vec<JsonValue>> for JsonValue {
+    fn from(value: Vec<JsonValue>) -> Self {
+        JsonValue::JsonArr(value)
+    }
+}
+
+impl From<JsonMap> for JsonValue {
+    fn from(value: JsonMap) -> Self {
+        JsonValue::JsonObj(value)
+    }
+}
+
+impl From<HashMap<String, JsonValue>> for JsonValue {
+    fn from(value: HashMap<String, JsonValue>) -> Self {
+        JsonValue::JsonObj(JsonMap::from(value))
+    }
+}
+
+/// Scalar tokens convert to the value they stand for, every other token is handed back.
+impl TryFrom<Token> for JsonValue {
+    type Error = Token;
+
+    fn try_from(token: Token) -> Result<Self, Self::Error> {
+        match token {
+            Token::JsonString(str) => Ok(JsonValue::JsonString(str)),
+            Token::JsonNum(num) => Ok(JsonValue::JsonNum(num)),
+            Token::JsonBool(bin) => Ok(JsonValue::JsonBool(bin)),
+            Token::JsonNull => Ok(JsonValue::JsonNull),
+            token => Err(token),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Runs the parsing tests of JSONTestSuite (https://github.com/nst/JSONTestSuite), vendored in
+//! `tests/data/JSONTestSuite`. Files starting with `y_` must be accepted, `n_` rejected, and
+//! `i_` are left to the implementation, the choices made here are listed in `ACCEPTED`.
+
+use std::{fs, path::PathBuf};
+
+use json_reader::{
+    parse_json_reader, parse_json_slice, parse_json_slice_with, parse_json_string, JsonError,
+    JsonErrorKind, ParserOptions,
+};
+
+// The implementation-defined cases that are accepted, every other `i_` file is rejected
+const ACCEPTED: &[(&str, &str)] = &[
+    // Numbers are read as f64. Ones too small for it become zero, and integers past 2^53 are
+    // rounded to the nearest f64. Ones too large are rejected with `NumberOutOfRange`, since
+    // infinity can't be written back as JSON
+    ("i_number_double_huge_neg_exp.json", "underflows to 0"),
+    ("i_number_real_underflow.json", "underflows to 0"),
+    ("i_number_too_big_neg_int.json", "rounded to an f64"),
+    ("i_number_too_big_pos_int.json", "rounded to an f64"),
+    ("i_number_very_big_negative_int.json", "rounded to an f64"),
+];
+
+fn test_files() -> Vec<(String, PathBuf)> {
+    let dir = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/JSONTestSuite/test_parsing"
+    );
+
+    let mut files: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, path)
+        })
+        .collect();
+    files.sort();
+
+    files
+}
+
+fn parse_file(path: &PathBuf, options: &ParserOptions) -> Result<(), JsonError> {
+    let bytes = fs::read(path).unwrap();
+
+    parse_json_slice_with(&bytes, options).map(|_| ())
+}
+
+#[test]
+fn follows_json_test_suite() {
+    let files = test_files();
+    assert_eq!(318, files.len());
+
+    let mut failures = Vec::new();
+    for (name, path) in &files {
+        let accepted = parse_file(path, &ParserOptions::default()).is_ok();
+        let expected = match &name[..2] {
+            "y_" => true,
+            "n_" => false,
+            _ => ACCEPTED.iter().any(|(accepted, _)| accepted == name),
+        };
+
+        if accepted != expected {
+            failures.push(name.as_str());
+        }
+    }
+
+    assert!(failures.is_empty(), "unexpected results for {:?}", failures);
+}
+
+// Why an implementation-defined case is rejected, None for a reason not documented here
+fn rejection_reason(error: &JsonError) -> Option<&'static str> {
+    match error.kind() {
+        // Like the UTF-16 and Latin-1 files
+        JsonErrorKind::InvalidUtf8 => Some("not UTF-8"),
+        // UTF-16 text starting with an ASCII character is valid UTF-8 up to its zero bytes
+        JsonErrorKind::UnexpectedCharacter('\0') => Some("UTF-16 read as UTF-8"),
+        JsonErrorKind::LoneSurrogate => Some("escape of a lone UTF-16 surrogate"),
+        // RFC 8259 lets parsers ignore a byte order mark, it is not whitespace though
+        JsonErrorKind::UnexpectedCharacter('\u{feff}') => Some("byte order mark"),
+        JsonErrorKind::TooDeep => Some("nested deeper than the default max_depth"),
+        JsonErrorKind::NumberOutOfRange => Some("number too large for an f64"),
+        _ => None,
+    }
+}
+
+#[test]
+fn rejects_implementation_defined_cases_for_documented_reasons() {
+    for (name, path) in test_files() {
+        if !name.starts_with("i_") || ACCEPTED.iter().any(|(accepted, _)| *accepted == name) {
+            continue;
+        }
+
+        let error = parse_file(&path, &ParserOptions::default()).unwrap_err();
+        assert!(
+            rejection_reason(&error).is_some(),
+            "{} rejected with {:?}",
+            name,
+            error
+        );
+    }
+}
+
+#[test]
+fn reads_every_file_the_same_from_a_reader() {
+    for (name, path) in test_files() {
+        let bytes = fs::read(&path).unwrap();
+
+        assert_eq!(
+            parse_json_slice(&bytes),
+            parse_json_reader(bytes.as_slice()),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn accepts_deep_nesting_when_the_limit_allows() {
+    let path = test_files()
+        .into_iter()
+        .find(|(name, _)| name == "i_structure_500_nested_arrays.json")
+        .unwrap()
+        .1;
+    let options = ParserOptions {
+        max_depth: 500,
+        ..ParserOptions::default()
+    };
+
+    assert!(parse_file(&path, &ParserOptions::default()).is_err());
+    assert!(parse_file(&path, &options).is_ok());
+}
+
+#[test]
+fn rejects_numbers_too_large_for_f64() {
+    let error = parse_json_string("[1.5e999]").unwrap_err();
+    assert_eq!(&JsonErrorKind::NumberOutOfRange, error.kind());
+    assert_eq!(1, error.offset());
+
+    let error = parse_json_string("[-1e400]").unwrap_err();
+    assert_eq!(&JsonErrorKind::NumberOutOfRange, error.kind());
+    assert_eq!(1, error.offset());
+
+    assert_eq!(
+        Ok(0.0),
+        parse_json_string("[123e-10000000]")
+            .map(|value| value.as_vec().unwrap()[0].as_f64().unwrap())
+    );
+}
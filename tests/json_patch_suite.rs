@@ -0,0 +1,76 @@
+//! Runs json-patch-tests (https://github.com/json-patch/json-patch-tests), vendored in
+//! `tests/data/json-patch-tests`. A test applies a patch to a document and gives either the
+//! expected result or an error, which can come from reading the patch or from applying it.
+//! Tests marked `disabled` are skipped, as upstream asks.
+
+use std::fs;
+
+use json_reader::{parse_json_slice, JsonPatch, JsonValue};
+
+fn read_tests(name: &str) -> Vec<JsonValue> {
+    let bytes = fs::read(format!(
+        "{}/tests/data/json-patch-tests/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap();
+    match parse_json_slice(&bytes).unwrap() {
+        JsonValue::JsonArr(tests) => tests,
+        _ => panic!("{} is not an array of tests", name),
+    }
+}
+
+fn run_file(name: &str) {
+    let mut failures = Vec::new();
+    for (i, test) in read_tests(name).iter().enumerate() {
+        if test.pointer("/disabled") == Ok(&JsonValue::JsonBool(true)) {
+            continue;
+        }
+
+        let comment = test
+            .pointer("/comment")
+            .or_else(|_| test.pointer("/error"))
+            .map_or_else(|_| format!("test {}", i), |comment| comment.to_string());
+        let mut document = test.pointer("/doc").unwrap().clone();
+        let result = JsonPatch::from_value(test.pointer("/patch").unwrap())
+            .and_then(|patch| document.apply_patch(&patch));
+
+        let passed = match (test.pointer("/expected"), test.pointer("/error")) {
+            (_, Ok(_)) => result.is_err() && document == *test.pointer("/doc").unwrap(),
+            (Ok(expected), _) => result.is_ok() && document == *expected,
+            _ => result.is_ok(),
+        };
+        if !passed {
+            failures.push(format!("{}: {:?} gave {}", comment, result, document));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn passes_the_general_tests() {
+    run_file("tests.json");
+}
+
+#[test]
+fn passes_the_rfc_examples() {
+    run_file("spec_tests.json");
+}
+
+#[test]
+fn diff_turns_each_document_into_its_expected_result() {
+    for name in ["tests.json", "spec_tests.json"] {
+        for test in &read_tests(name) {
+            let (Ok(doc), Ok(expected)) = (test.pointer("/doc"), test.pointer("/expected")) else {
+                continue;
+            };
+
+            let mut patched = doc.clone();
+            patched
+                .apply_patch(&JsonPatch::diff(doc, expected))
+                .unwrap();
+            assert_eq!(*expected, patched);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Runs the JSONPath Compliance Test Suite (https://github.com/jsonpath-standard/jsonpath-compliance-test-suite),
+//! vendored in `tests/data/jsonpath-compliance-test-suite`. Each test either has a selector that
+//! must be rejected, or a document along with the values and normalized paths the selector
+//! gives. Where RFC 9535 leaves the order open, like for the members of an object, a test lists
+//! every result allowed.
+
+use std::fs;
+
+use json_reader::{parse_json_slice, JsonPath, JsonValue};
+
+fn items(value: &JsonValue) -> &[JsonValue] {
+    match value {
+        JsonValue::JsonArr(vector) => vector,
+        _ => panic!("expected an array, found {:?}", value),
+    }
+}
+
+fn paths(paths: &JsonValue) -> Vec<&str> {
+    items(paths)
+        .iter()
+        .map(|path| path.as_str().unwrap())
+        .collect()
+}
+
+// The values and paths of the results a test allows
+fn expected_results(test: &JsonValue) -> Vec<(&[JsonValue], Vec<&str>)> {
+    match (test.pointer("/result"), test.pointer("/result_paths")) {
+        (Ok(values), Ok(result_paths)) => vec![(items(values), paths(result_paths))],
+        _ => items(test.pointer("/results").unwrap())
+            .iter()
+            .zip(items(test.pointer("/results_paths").unwrap()))
+            .map(|(values, result_paths)| (items(values), paths(result_paths)))
+            .collect(),
+    }
+}
+
+#[test]
+fn follows_the_compliance_test_suite() {
+    let bytes = fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/jsonpath-compliance-test-suite/cts.json"
+    ))
+    .unwrap();
+    let suite = parse_json_slice(&bytes).unwrap();
+    let tests = items(suite.pointer("/tests").unwrap());
+    assert_eq!(703, tests.len());
+
+    let mut failures = Vec::new();
+    for test in tests {
+        let name = test.pointer("/name").unwrap().as_str().unwrap();
+        let selector = test.pointer("/selector").unwrap().as_str().unwrap();
+        let path = JsonPath::parse(selector);
+
+        if test.pointer("/invalid_selector").is_ok() {
+            if path.is_ok() {
+                failures.push(format!("{}: {:?} accepted", name, selector));
+            }
+            continue;
+        }
+
+        let path = match path {
+            Ok(path) => path,
+            Err(error) => {
+                failures.push(format!("{}: {:?} rejected, {}", name, selector, error));
+                continue;
+            }
+        };
+        let nodes = path.query(test.pointer("/document").unwrap());
+        let values: Vec<JsonValue> = nodes.iter().map(|node| node.value().clone()).collect();
+        let paths: Vec<String> = nodes.iter().map(|node| node.normalized_path()).collect();
+
+        let matches =
+            expected_results(test)
+                .into_iter()
+                .any(|(expected_values, expected_paths)| {
+                    values == expected_values && paths == expected_paths
+                });
+        if !matches {
+            failures.push(format!("{}: {:?} gave {:?}", name, selector, paths));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} failures:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}